@@ -0,0 +1,1181 @@
+//! The ext2 filesystem is a classic, on-disk filesystem used by Linux. This module implements a
+//! read/write `Ext2FS` on top of a block device exposed as an `IO` interface.
+
+use core::mem::size_of;
+use crate::errno;
+use crate::file::Errno;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::Gid;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::file::Uid;
+use crate::file::fs::Filesystem;
+use crate::file::fs::FilesystemType;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::util::IO;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::ptr::SharedPtr;
+
+/// The magic number identifying an ext2 superblock.
+const EXT2_MAGIC: u16 = 0xef53;
+/// The byte offset of the superblock on the device.
+const SUPERBLOCK_OFFSET: u64 = 1024;
+/// The size of the superblock structure, in bytes.
+const SUPERBLOCK_SIZE: usize = 1024;
+
+/// The number of direct block pointers in an inode.
+const DIRECT_BLOCKS: usize = 12;
+
+/// Root directory's inode.
+pub const ROOT_INODE: INode = 2;
+
+/// The on-disk ext2 superblock.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+struct Superblock {
+	total_inodes: u32,
+	total_blocks: u32,
+	reserved_blocks: u32,
+	free_blocks: u32,
+	free_inodes: u32,
+	first_data_block: u32,
+	log_block_size: u32,
+	log_frag_size: u32,
+	blocks_per_group: u32,
+	frags_per_group: u32,
+	inodes_per_group: u32,
+	mtime: u32,
+	wtime: u32,
+	mnt_count: u16,
+	max_mnt_count: u16,
+	magic: u16,
+	state: u16,
+	errors: u16,
+	minor_rev_level: u16,
+	lastcheck: u32,
+	checkinterval: u32,
+	creator_os: u32,
+	rev_level: u32,
+	def_resuid: u16,
+	def_resgid: u16,
+	// Extended fields (rev >= 1), zeroed on rev 0 filesystems.
+	first_ino: u32,
+	inode_size: u16,
+	block_group_nr: u16,
+	feature_compat: u32,
+	feature_incompat: u32,
+	feature_ro_compat: u32,
+}
+
+impl Superblock {
+	/// Returns the size of a block in bytes.
+	fn block_size(&self) -> u64 {
+		1024 << self.log_block_size
+	}
+
+	/// Returns the number of block groups.
+	fn groups_count(&self) -> u32 {
+		(self.total_blocks + self.blocks_per_group - 1) / self.blocks_per_group
+	}
+
+	/// Returns the size in bytes of an on-disk inode.
+	fn inode_size(&self) -> u64 {
+		if self.rev_level >= 1 && self.inode_size > 0 {
+			self.inode_size as u64
+		} else {
+			128
+		}
+	}
+
+	/// Returns the index of the first non-reserved inode.
+	fn first_non_reserved_inode(&self) -> u32 {
+		if self.rev_level >= 1 && self.first_ino > 0 {
+			self.first_ino
+		} else {
+			11
+		}
+	}
+}
+
+/// The on-disk block group descriptor.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+struct BlockGroupDescriptor {
+	block_bitmap: u32,
+	inode_bitmap: u32,
+	inode_table: u32,
+	free_blocks_count: u16,
+	free_inodes_count: u16,
+	used_dirs_count: u16,
+	pad: u16,
+	reserved: [u8; 12],
+}
+
+/// The on-disk inode representation.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+struct DiskInode {
+	mode: u16,
+	uid: u16,
+	size_lo: u32,
+	atime: u32,
+	ctime: u32,
+	mtime: u32,
+	dtime: u32,
+	gid: u16,
+	links_count: u16,
+	blocks: u32,
+	flags: u32,
+	osd1: u32,
+	block: [u32; DIRECT_BLOCKS + 3],
+	generation: u32,
+	file_acl: u32,
+	size_hi: u32,
+	faddr: u32,
+	osd2: [u8; 12],
+}
+
+/// An on-disk ext2 directory entry (`ext2_dir_entry`), variable-length and padded so the next
+/// entry starts on a 4-byte boundary.
+#[repr(C, packed)]
+struct RawDirEntry {
+	inode: u32,
+	rec_len: u16,
+	name_len: u8,
+	file_type: u8,
+	// Followed by `name_len` bytes of the entry's name (not NUL-terminated).
+}
+
+/// Structure representing a mounted ext2 filesystem.
+pub struct Ext2FS {
+	superblock: Superblock,
+	bgdt: Vec<BlockGroupDescriptor>,
+
+	fs_id: u32,
+	readonly: bool,
+	mountpath: Path,
+}
+
+impl Ext2FS {
+	/// Reads the raw superblock located at byte `SUPERBLOCK_OFFSET` on `io`.
+	fn read_superblock(io: &mut dyn IO) -> Result<Superblock, Errno> {
+		let mut buf = [0u8; SUPERBLOCK_SIZE];
+		io.read(SUPERBLOCK_OFFSET, &mut buf)?;
+
+		// Safe because `Superblock` is `repr(C, packed)` and every bit pattern of its fields is
+		// valid.
+		Ok(unsafe { *(buf.as_ptr() as *const Superblock) })
+	}
+
+	/// Reads the block group descriptor table, located right after the superblock's block.
+	fn read_bgdt(io: &mut dyn IO, superblock: &Superblock) -> Result<Vec<BlockGroupDescriptor>, Errno> {
+		let bgdt_block = if superblock.block_size() == 1024 { 2 } else { 1 };
+		let off = bgdt_block * superblock.block_size();
+
+		let count = superblock.groups_count() as usize;
+		let mut bgdt = Vec::with_capacity(count)?;
+
+		let mut buf = [0u8; size_of::<BlockGroupDescriptor>()];
+		for i in 0..count {
+			io.read(off + (i * buf.len()) as u64, &mut buf)?;
+			let desc = unsafe { *(buf.as_ptr() as *const BlockGroupDescriptor) };
+			bgdt.push(desc)?;
+		}
+
+		Ok(bgdt)
+	}
+
+	/// Writes the in-memory superblock back to `io`.
+	fn write_superblock(&self, io: &mut dyn IO) -> Result<(), Errno> {
+		let buf = unsafe {
+			core::slice::from_raw_parts(&self.superblock as *const _ as *const u8,
+				size_of::<Superblock>())
+		};
+		io.write(SUPERBLOCK_OFFSET, buf)?;
+		Ok(())
+	}
+
+	/// Writes the block group descriptor of group `group` back to `io`.
+	fn write_bgdt_entry(&self, io: &mut dyn IO, group: usize) -> Result<(), Errno> {
+		let bgdt_block = if self.superblock.block_size() == 1024 { 2 } else { 1 };
+		let off = bgdt_block * self.superblock.block_size()
+			+ (group * size_of::<BlockGroupDescriptor>()) as u64;
+
+		let desc = self.bgdt.get(group).ok_or_else(|| errno!(EINVAL))?;
+		let buf = unsafe {
+			core::slice::from_raw_parts(desc as *const _ as *const u8,
+				size_of::<BlockGroupDescriptor>())
+		};
+		io.write(off, buf)?;
+		Ok(())
+	}
+
+	/// Writes `node` back to the on-disk inode `inode`.
+	fn write_inode(&self, io: &mut dyn IO, inode: INode, node: &DiskInode) -> Result<(), Errno> {
+		let off = self.inode_offset(inode)?;
+		let buf = unsafe {
+			core::slice::from_raw_parts(node as *const _ as *const u8, size_of::<DiskInode>())
+		};
+		io.write(off, buf)?;
+		Ok(())
+	}
+
+	/// Reads the value of bit `bit` of the bitmap located at block `bitmap_block`.
+	fn read_bitmap_bit(&self, io: &mut dyn IO, bitmap_block: u32, bit: u64) -> Result<bool, Errno> {
+		let off = bitmap_block as u64 * self.superblock.block_size() + bit / 8;
+		let mut byte = [0u8; 1];
+		io.read(off, &mut byte)?;
+
+		Ok(byte[0] & (1 << (bit % 8)) != 0)
+	}
+
+	/// Sets the value of bit `bit` of the bitmap located at block `bitmap_block` to `value`.
+	fn write_bitmap_bit(&self, io: &mut dyn IO, bitmap_block: u32, bit: u64, value: bool)
+		-> Result<(), Errno> {
+		let off = bitmap_block as u64 * self.superblock.block_size() + bit / 8;
+		let mut byte = [0u8; 1];
+		io.read(off, &mut byte)?;
+
+		if value {
+			byte[0] |= 1 << (bit % 8);
+		} else {
+			byte[0] &= !(1 << (bit % 8));
+		}
+
+		io.write(off, &byte)?;
+		Ok(())
+	}
+
+	/// Allocates a free block, returning its number.
+	///
+	/// The search starts at the first block group and stops at the first block found free in
+	/// its bitmap; the superblock's and the owning group's free block counters are updated and
+	/// written back to disk.
+	fn alloc_block(&mut self, io: &mut dyn IO) -> Result<u32, Errno> {
+		let blocks_per_group = self.superblock.blocks_per_group as u64;
+
+		for group in 0..self.bgdt.len() {
+			if self.bgdt[group].free_blocks_count == 0 {
+				continue;
+			}
+
+			let bitmap_block = self.bgdt[group].block_bitmap;
+			for bit in 0..blocks_per_group {
+				let block = self.superblock.first_data_block as u64
+					+ group as u64 * blocks_per_group + bit;
+				if block >= self.superblock.total_blocks as u64 {
+					break;
+				}
+
+				if !self.read_bitmap_bit(io, bitmap_block, bit)? {
+					self.write_bitmap_bit(io, bitmap_block, bit, true)?;
+
+					self.bgdt[group].free_blocks_count -= 1;
+					self.write_bgdt_entry(io, group)?;
+
+					self.superblock.free_blocks -= 1;
+					self.write_superblock(io)?;
+
+					// Newly allocated blocks are handed out zeroed, same as a sparse hole reads
+					// as zero, so callers never see stale data left over from a previous owner.
+					let zeroes = crate::vec![0u8; self.superblock.block_size() as usize]?;
+					io.write(block * self.superblock.block_size(), &zeroes)?;
+
+					return Ok(block as u32);
+				}
+			}
+		}
+
+		Err(errno!(ENOSPC))
+	}
+
+	/// Frees the block `block`, returning it to its group's bitmap.
+	fn free_block(&mut self, io: &mut dyn IO, block: u32) -> Result<(), Errno> {
+		let blocks_per_group = self.superblock.blocks_per_group;
+		let index = block - self.superblock.first_data_block;
+		let group = (index / blocks_per_group) as usize;
+		let bit = (index % blocks_per_group) as u64;
+
+		let bitmap_block = self.bgdt.get(group).ok_or_else(|| errno!(EINVAL))?.block_bitmap;
+		self.write_bitmap_bit(io, bitmap_block, bit, false)?;
+
+		self.bgdt[group].free_blocks_count += 1;
+		self.write_bgdt_entry(io, group)?;
+
+		self.superblock.free_blocks += 1;
+		self.write_superblock(io)
+	}
+
+	/// Allocates a free, non-reserved inode, returning its number.
+	fn alloc_inode(&mut self, io: &mut dyn IO) -> Result<INode, Errno> {
+		let inodes_per_group = self.superblock.inodes_per_group as u64;
+		let first_non_reserved = self.first_non_reserved_inode() as u64;
+
+		for group in 0..self.bgdt.len() {
+			if self.bgdt[group].free_inodes_count == 0 {
+				continue;
+			}
+
+			let bitmap_block = self.bgdt[group].inode_bitmap;
+			for bit in 0..inodes_per_group {
+				let inode = group as u64 * inodes_per_group + bit + 1;
+				if inode as u32 > self.superblock.total_inodes {
+					break;
+				}
+				// Reserved inodes (below the first non-reserved one) are never handed out.
+				if inode < first_non_reserved {
+					continue;
+				}
+
+				if !self.read_bitmap_bit(io, bitmap_block, bit)? {
+					self.write_bitmap_bit(io, bitmap_block, bit, true)?;
+
+					self.bgdt[group].free_inodes_count -= 1;
+					self.write_bgdt_entry(io, group)?;
+
+					self.superblock.free_inodes -= 1;
+					self.write_superblock(io)?;
+
+					return Ok(inode as INode);
+				}
+			}
+		}
+
+		Err(errno!(ENOSPC))
+	}
+
+	/// Frees the inode `inode`, returning it to its group's bitmap.
+	fn free_inode(&mut self, io: &mut dyn IO, inode: INode) -> Result<(), Errno> {
+		let inodes_per_group = self.superblock.inodes_per_group as u64;
+		let index = (inode as u64) - 1;
+		let group = (index / inodes_per_group) as usize;
+		let bit = index % inodes_per_group;
+
+		let bitmap_block = self.bgdt.get(group).ok_or_else(|| errno!(EINVAL))?.inode_bitmap;
+		self.write_bitmap_bit(io, bitmap_block, bit, false)?;
+
+		self.bgdt[group].free_inodes_count += 1;
+		self.write_bgdt_entry(io, group)?;
+
+		self.superblock.free_inodes += 1;
+		self.write_superblock(io)
+	}
+
+	/// Creates a new instance by parsing the filesystem already present on `io`.
+	pub fn mount(io: &mut dyn IO, fs_id: u32, readonly: bool, mountpath: Path)
+		-> Result<Self, Errno> {
+		let superblock = Self::read_superblock(io)?;
+		if superblock.magic != EXT2_MAGIC {
+			return Err(errno!(EINVAL));
+		}
+
+		// A block size the VFS cannot reasonably hold in one contiguous buffer isn't supported.
+		let block_size = superblock.block_size();
+		if block_size < 1024 || block_size > crate::memory::PAGE_SIZE as u64 {
+			return Err(errno!(EINVAL));
+		}
+
+		let bgdt = Self::read_bgdt(io, &superblock)?;
+
+		Ok(Self {
+			superblock,
+			bgdt,
+
+			fs_id,
+			readonly,
+			mountpath,
+		})
+	}
+
+	/// Returns the (group index, index within group) pair for inode `inode`.
+	fn inode_group(&self, inode: INode) -> (usize, u64) {
+		let index = (inode as u64) - 1;
+		let group = index / self.superblock.inodes_per_group as u64;
+		let in_group = index % self.superblock.inodes_per_group as u64;
+		(group as usize, in_group)
+	}
+
+	/// Returns the byte offset of the on-disk inode `inode`.
+	fn inode_offset(&self, inode: INode) -> Result<u64, Errno> {
+		let (group, in_group) = self.inode_group(inode);
+		let desc = self.bgdt.get(group).ok_or_else(|| errno!(EINVAL))?;
+
+		let table_off = desc.inode_table as u64 * self.superblock.block_size();
+		Ok(table_off + in_group * self.superblock.inode_size())
+	}
+
+	/// Reads the on-disk inode `inode`.
+	fn read_inode(&self, io: &mut dyn IO, inode: INode) -> Result<DiskInode, Errno> {
+		let off = self.inode_offset(inode)?;
+		let mut buf = [0u8; size_of::<DiskInode>()];
+		io.read(off, &mut buf)?;
+
+		Ok(unsafe { *(buf.as_ptr() as *const DiskInode) })
+	}
+
+	/// Returns the size in bytes of the file represented by `node`.
+	fn inode_size(node: &DiskInode) -> u64 {
+		(node.size_hi as u64) << 32 | node.size_lo as u64
+	}
+
+	/// Resolves the data block holding the byte at file offset `off` in `node`, walking the
+	/// direct, single, double and triple indirect pointers as needed.
+	///
+	/// A `0` block pointer denotes a sparse hole; the function returns `None` in that case,
+	/// letting the caller fill the range with zeros instead of reading from disk.
+	fn resolve_block(&self, io: &mut dyn IO, node: &DiskInode, off: u64) -> Result<Option<u32>, Errno> {
+		let block_size = self.superblock.block_size();
+		let ptrs_per_block = block_size / size_of::<u32>() as u64;
+		let mut block_idx = off / block_size;
+
+		let read_ptr = |io: &mut dyn IO, block: u32, idx: u64| -> Result<u32, Errno> {
+			let mut buf = [0u8; 4];
+			io.read(block as u64 * block_size + idx * 4, &mut buf)?;
+			Ok(u32::from_le_bytes(buf))
+		};
+
+		if block_idx < DIRECT_BLOCKS as u64 {
+			let b = node.block[block_idx as usize];
+			return Ok((b != 0).then_some(b));
+		}
+		block_idx -= DIRECT_BLOCKS as u64;
+
+		// Single indirect
+		if block_idx < ptrs_per_block {
+			let indirect = node.block[DIRECT_BLOCKS];
+			if indirect == 0 {
+				return Ok(None);
+			}
+			let b = read_ptr(io, indirect, block_idx)?;
+			return Ok((b != 0).then_some(b));
+		}
+		block_idx -= ptrs_per_block;
+
+		// Double indirect
+		if block_idx < ptrs_per_block * ptrs_per_block {
+			let indirect = node.block[DIRECT_BLOCKS + 1];
+			if indirect == 0 {
+				return Ok(None);
+			}
+			let l1 = read_ptr(io, indirect, block_idx / ptrs_per_block)?;
+			if l1 == 0 {
+				return Ok(None);
+			}
+			let b = read_ptr(io, l1, block_idx % ptrs_per_block)?;
+			return Ok((b != 0).then_some(b));
+		}
+		block_idx -= ptrs_per_block * ptrs_per_block;
+
+		// Triple indirect
+		let indirect = node.block[DIRECT_BLOCKS + 2];
+		if indirect == 0 {
+			return Ok(None);
+		}
+		let l1 = read_ptr(io, indirect, block_idx / (ptrs_per_block * ptrs_per_block))?;
+		if l1 == 0 {
+			return Ok(None);
+		}
+		let l2_idx = (block_idx / ptrs_per_block) % ptrs_per_block;
+		let l2 = read_ptr(io, l1, l2_idx)?;
+		if l2 == 0 {
+			return Ok(None);
+		}
+		let b = read_ptr(io, l2, block_idx % ptrs_per_block)?;
+		Ok((b != 0).then_some(b))
+	}
+
+	/// Like [`Self::resolve_block`], but allocates the block (and any missing indirect tables)
+	/// if not already present, returning its number.
+	///
+	/// `node`'s direct/indirect pointers are updated in memory; the caller is responsible for
+	/// writing `node` back to disk afterward.
+	fn resolve_block_alloc(&mut self, io: &mut dyn IO, node: &mut DiskInode, off: u64)
+		-> Result<u32, Errno> {
+		let block_size = self.superblock.block_size();
+		let ptrs_per_block = block_size / size_of::<u32>() as u64;
+		let mut block_idx = off / block_size;
+
+		let read_ptr = |io: &mut dyn IO, block: u32, idx: u64| -> Result<u32, Errno> {
+			let mut buf = [0u8; 4];
+			io.read(block as u64 * block_size + idx * 4, &mut buf)?;
+			Ok(u32::from_le_bytes(buf))
+		};
+		let write_ptr = |io: &mut dyn IO, block: u32, idx: u64, value: u32| -> Result<(), Errno> {
+			io.write(block as u64 * block_size + idx * 4, &value.to_le_bytes())
+		};
+
+		if block_idx < DIRECT_BLOCKS as u64 {
+			if node.block[block_idx as usize] == 0 {
+				node.block[block_idx as usize] = self.alloc_block(io)?;
+				node.blocks += (block_size / 512) as u32;
+			}
+			return Ok(node.block[block_idx as usize]);
+		}
+		block_idx -= DIRECT_BLOCKS as u64;
+
+		// Single indirect
+		if block_idx < ptrs_per_block {
+			if node.block[DIRECT_BLOCKS] == 0 {
+				node.block[DIRECT_BLOCKS] = self.alloc_block(io)?;
+				node.blocks += (block_size / 512) as u32;
+			}
+			let indirect = node.block[DIRECT_BLOCKS];
+
+			let mut b = read_ptr(io, indirect, block_idx)?;
+			if b == 0 {
+				b = self.alloc_block(io)?;
+				node.blocks += (block_size / 512) as u32;
+				write_ptr(io, indirect, block_idx, b)?;
+			}
+			return Ok(b);
+		}
+		block_idx -= ptrs_per_block;
+
+		// Double indirect
+		if block_idx < ptrs_per_block * ptrs_per_block {
+			if node.block[DIRECT_BLOCKS + 1] == 0 {
+				node.block[DIRECT_BLOCKS + 1] = self.alloc_block(io)?;
+				node.blocks += (block_size / 512) as u32;
+			}
+			let indirect = node.block[DIRECT_BLOCKS + 1];
+
+			let l1_idx = block_idx / ptrs_per_block;
+			let mut l1 = read_ptr(io, indirect, l1_idx)?;
+			if l1 == 0 {
+				l1 = self.alloc_block(io)?;
+				node.blocks += (block_size / 512) as u32;
+				write_ptr(io, indirect, l1_idx, l1)?;
+			}
+
+			let l2_idx = block_idx % ptrs_per_block;
+			let mut b = read_ptr(io, l1, l2_idx)?;
+			if b == 0 {
+				b = self.alloc_block(io)?;
+				node.blocks += (block_size / 512) as u32;
+				write_ptr(io, l1, l2_idx, b)?;
+			}
+			return Ok(b);
+		}
+		block_idx -= ptrs_per_block * ptrs_per_block;
+
+		// Triple indirect
+		if node.block[DIRECT_BLOCKS + 2] == 0 {
+			node.block[DIRECT_BLOCKS + 2] = self.alloc_block(io)?;
+			node.blocks += (block_size / 512) as u32;
+		}
+		let indirect = node.block[DIRECT_BLOCKS + 2];
+
+		let l1_idx = block_idx / (ptrs_per_block * ptrs_per_block);
+		let mut l1 = read_ptr(io, indirect, l1_idx)?;
+		if l1 == 0 {
+			l1 = self.alloc_block(io)?;
+			node.blocks += (block_size / 512) as u32;
+			write_ptr(io, indirect, l1_idx, l1)?;
+		}
+
+		let l2_idx = (block_idx / ptrs_per_block) % ptrs_per_block;
+		let mut l2 = read_ptr(io, l1, l2_idx)?;
+		if l2 == 0 {
+			l2 = self.alloc_block(io)?;
+			node.blocks += (block_size / 512) as u32;
+			write_ptr(io, l1, l2_idx, l2)?;
+		}
+
+		let l3_idx = block_idx % ptrs_per_block;
+		let mut b = read_ptr(io, l2, l3_idx)?;
+		if b == 0 {
+			b = self.alloc_block(io)?;
+			node.blocks += (block_size / 512) as u32;
+			write_ptr(io, l2, l3_idx, b)?;
+		}
+
+		Ok(b)
+	}
+
+	/// Writes `buf` into the file represented by `node`, starting at offset `off`, allocating
+	/// new blocks as the file grows.
+	///
+	/// `node`'s size and block pointers are updated in memory; the caller is responsible for
+	/// writing `node` back to disk afterward.
+	fn write_inode_data(&mut self, io: &mut dyn IO, node: &mut DiskInode, off: u64, buf: &[u8])
+		-> Result<(), Errno> {
+		let block_size = self.superblock.block_size();
+
+		let mut written = 0;
+		while written < buf.len() as u64 {
+			let file_off = off + written;
+			let block_off = file_off % block_size;
+			let chunk = core::cmp::min(block_size - block_off, buf.len() as u64 - written);
+
+			let block = self.resolve_block_alloc(io, node, file_off)?;
+			io.write(block as u64 * block_size + block_off,
+				&buf[(written as usize)..(written as usize + chunk as usize)])?;
+
+			written += chunk;
+		}
+
+		let new_size = off + buf.len() as u64;
+		if new_size > Self::inode_size(node) {
+			node.size_lo = new_size as u32;
+			node.size_hi = (new_size >> 32) as u32;
+		}
+
+		Ok(())
+	}
+
+	/// Frees every data block (direct and indirect) owned by `node`.
+	fn free_inode_blocks(&mut self, io: &mut dyn IO, node: &DiskInode) -> Result<(), Errno> {
+		let block_size = self.superblock.block_size();
+		let ptrs_per_block = (block_size / size_of::<u32>() as u64) as usize;
+
+		let read_ptr = |io: &mut dyn IO, block: u32, idx: usize| -> Result<u32, Errno> {
+			let mut buf = [0u8; 4];
+			io.read(block as u64 * block_size + (idx * 4) as u64, &mut buf)?;
+			Ok(u32::from_le_bytes(buf))
+		};
+
+		for i in 0..DIRECT_BLOCKS {
+			if node.block[i] != 0 {
+				self.free_block(io, node.block[i])?;
+			}
+		}
+
+		if node.block[DIRECT_BLOCKS] != 0 {
+			let indirect = node.block[DIRECT_BLOCKS];
+			for i in 0..ptrs_per_block {
+				let b = read_ptr(io, indirect, i)?;
+				if b != 0 {
+					self.free_block(io, b)?;
+				}
+			}
+			self.free_block(io, indirect)?;
+		}
+
+		if node.block[DIRECT_BLOCKS + 1] != 0 {
+			let l0 = node.block[DIRECT_BLOCKS + 1];
+			for i in 0..ptrs_per_block {
+				let l1 = read_ptr(io, l0, i)?;
+				if l1 == 0 {
+					continue;
+				}
+				for j in 0..ptrs_per_block {
+					let b = read_ptr(io, l1, j)?;
+					if b != 0 {
+						self.free_block(io, b)?;
+					}
+				}
+				self.free_block(io, l1)?;
+			}
+			self.free_block(io, l0)?;
+		}
+
+		if node.block[DIRECT_BLOCKS + 2] != 0 {
+			let l0 = node.block[DIRECT_BLOCKS + 2];
+			for i in 0..ptrs_per_block {
+				let l1 = read_ptr(io, l0, i)?;
+				if l1 == 0 {
+					continue;
+				}
+				for j in 0..ptrs_per_block {
+					let l2 = read_ptr(io, l1, j)?;
+					if l2 == 0 {
+						continue;
+					}
+					for k in 0..ptrs_per_block {
+						let b = read_ptr(io, l2, k)?;
+						if b != 0 {
+							self.free_block(io, b)?;
+						}
+					}
+					self.free_block(io, l2)?;
+				}
+				self.free_block(io, l1)?;
+			}
+			self.free_block(io, l0)?;
+		}
+
+		Ok(())
+	}
+
+	/// Writes a raw directory entry (header + name) at offset `off` in the directory
+	/// represented by `node`, with `rec_len` as its on-disk record length.
+	fn write_raw_dir_entry(&mut self, io: &mut dyn IO, node: &mut DiskInode, off: u64,
+		rec_len: u16, name: &[u8], inode: INode, file_type: u8) -> Result<(), Errno> {
+		let hdr = RawDirEntry {
+			inode,
+			rec_len,
+			name_len: name.len() as u8,
+			file_type,
+		};
+		let hdr_buf = unsafe {
+			core::slice::from_raw_parts(&hdr as *const _ as *const u8, size_of::<RawDirEntry>())
+		};
+
+		self.write_inode_data(io, node, off, hdr_buf)?;
+		self.write_inode_data(io, node, off + hdr_buf.len() as u64, name)?;
+
+		Ok(())
+	}
+
+	/// Appends a directory entry (`name` -> `inode`) into the directory represented by `node`.
+	///
+	/// Slack space left at the end of an existing entry (most commonly the last entry of a
+	/// block, whose `rec_len` stretches to the block's end) is reused if large enough; otherwise
+	/// the directory grows by one full block, which the new entry claims whole.
+	fn append_dir_entry(&mut self, io: &mut dyn IO, node: &mut DiskInode, name: &[u8],
+		inode: INode, file_type: u8) -> Result<(), Errno> {
+		let block_size = self.superblock.block_size();
+		let hdr_len = size_of::<RawDirEntry>() as u64;
+		let needed = (hdr_len + name.len() as u64 + 3) & !3;
+
+		let size = Self::inode_size(node);
+		let mut buf = [0u8; size_of::<RawDirEntry>() + 255];
+		let mut off = 0;
+
+		while off < size {
+			self.read_inode_data(io, node, off, &mut buf[..hdr_len as usize])?;
+			let entry = unsafe { *(buf.as_ptr() as *const RawDirEntry) };
+			let rec_len = entry.rec_len as u64;
+			if rec_len == 0 {
+				break;
+			}
+
+			let used = if entry.inode == 0 {
+				0
+			} else {
+				(hdr_len + entry.name_len as u64 + 3) & !3
+			};
+			let slack = rec_len - used;
+
+			if slack >= needed {
+				if entry.inode != 0 {
+					let name_len = entry.name_len as usize;
+					self.read_inode_data(io, node, off + hdr_len,
+						&mut buf[(hdr_len as usize)..(hdr_len as usize + name_len)])?;
+
+					self.write_raw_dir_entry(io, node, off, used as u16,
+						&buf[(hdr_len as usize)..(hdr_len as usize + name_len)], entry.inode,
+						entry.file_type)?;
+					self.write_raw_dir_entry(io, node, off + used, (slack) as u16, name, inode,
+						file_type)?;
+				} else {
+					self.write_raw_dir_entry(io, node, off, rec_len as u16, name, inode,
+						file_type)?;
+				}
+
+				return Ok(());
+			}
+
+			off += rec_len;
+		}
+
+		let end = size.div_ceil(block_size) * block_size;
+		self.write_raw_dir_entry(io, node, end, block_size as u16, name, inode, file_type)?;
+		node.size_lo = (end + block_size) as u32;
+		node.size_hi = ((end + block_size) >> 32) as u32;
+
+		Ok(())
+	}
+
+	/// Finds the entry named `name` in the directory represented by `node`, zeroing its inode
+	/// field so it reads as removed (its slack becomes available to later
+	/// [`Self::append_dir_entry`] calls), and returns the inode it pointed to.
+	fn remove_dir_entry(&mut self, io: &mut dyn IO, node: &mut DiskInode, name: &[u8])
+		-> Result<INode, Errno> {
+		let hdr_len = size_of::<RawDirEntry>() as u64;
+		let size = Self::inode_size(node);
+		let mut buf = [0u8; size_of::<RawDirEntry>() + 255];
+		let mut off = 0;
+
+		while off < size {
+			self.read_inode_data(io, node, off, &mut buf[..hdr_len as usize])?;
+			let entry = unsafe { *(buf.as_ptr() as *const RawDirEntry) };
+			let rec_len = entry.rec_len as u64;
+			if rec_len == 0 {
+				break;
+			}
+
+			if entry.inode != 0 {
+				let name_len = entry.name_len as usize;
+				self.read_inode_data(io, node, off + hdr_len,
+					&mut buf[(hdr_len as usize)..(hdr_len as usize + name_len)])?;
+
+				if &buf[(hdr_len as usize)..(hdr_len as usize + name_len)] == name {
+					let removed = entry.inode;
+					self.write_inode_data(io, node, off, &0u32.to_le_bytes())?;
+					return Ok(removed);
+				}
+			}
+
+			off += rec_len;
+		}
+
+		Err(errno!(ENOENT))
+	}
+
+	/// Reads `buf.len()` bytes of the file represented by `node`, starting at offset `off`.
+	fn read_inode_data(&self, io: &mut dyn IO, node: &DiskInode, off: u64, buf: &mut [u8])
+		-> Result<u64, Errno> {
+		let size = Self::inode_size(node);
+		if off >= size {
+			return Ok(0);
+		}
+
+		let block_size = self.superblock.block_size();
+		let len = core::cmp::min(buf.len() as u64, size - off);
+		let mut read = 0;
+		while read < len {
+			let file_off = off + read;
+			let block_off = file_off % block_size;
+			let chunk = core::cmp::min(block_size - block_off, len - read);
+
+			match self.resolve_block(io, node, file_off)? {
+				Some(block) => {
+					io.read(block as u64 * block_size + block_off,
+						&mut buf[(read as usize)..(read as usize + chunk as usize)])?;
+				}
+				// Sparse hole: reads as zeros.
+				None => {
+					buf[(read as usize)..(read as usize + chunk as usize)].fill(0);
+				}
+			}
+
+			read += chunk;
+		}
+
+		Ok(read)
+	}
+
+	/// Iterates over the directory entries of `node`, calling `f` with each entry's inode, type
+	/// and name.
+	fn iter_dir<F: FnMut(INode, FileType, &[u8]) -> Result<(), Errno>>(&self, io: &mut dyn IO,
+		node: &DiskInode, mut f: F) -> Result<(), Errno> {
+		let size = Self::inode_size(node);
+		let mut off = 0;
+		let mut buf = [0u8; size_of::<RawDirEntry>() + 255];
+
+		while off < size {
+			let hdr_len = size_of::<RawDirEntry>();
+			self.read_inode_data(io, node, off, &mut buf[..hdr_len])?;
+			let entry = unsafe { &*(buf.as_ptr() as *const RawDirEntry) };
+			let rec_len = entry.rec_len as u64;
+			if rec_len == 0 {
+				break;
+			}
+
+			if entry.inode != 0 {
+				let name_len = entry.name_len as usize;
+				self.read_inode_data(io, node, off + hdr_len as u64,
+					&mut buf[hdr_len..(hdr_len + name_len)])?;
+				let file_type = match entry.file_type {
+					1 => FileType::Regular,
+					2 => FileType::Directory,
+					7 => FileType::Link,
+					_ => FileType::Regular,
+				};
+				f(entry.inode, file_type, &buf[hdr_len..(hdr_len + name_len)])?;
+			}
+
+			off += rec_len;
+		}
+
+		Ok(())
+	}
+}
+
+impl Filesystem for Ext2FS {
+	fn get_name(&self) -> &[u8] {
+		b"ext2"
+	}
+
+	fn get_id(&self) -> u32 {
+		self.fs_id
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.readonly
+	}
+
+	fn must_cache(&self) -> bool {
+		true
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		Ok(Statfs {
+			f_type: EXT2_MAGIC as _,
+			f_bsize: self.superblock.block_size() as _,
+			f_blocks: self.superblock.total_blocks as _,
+			f_bfree: self.superblock.free_blocks as _,
+			// On a nearly-full filesystem, the reserved-blocks floor can exceed what's actually
+			// still free; saturate instead of underflowing, since there are then no blocks left
+			// that an unprivileged caller could use.
+			f_bavail: self.superblock.free_blocks.saturating_sub(self.superblock.reserved_blocks) as _,
+			f_files: self.superblock.total_inodes as _,
+			f_ffree: self.superblock.free_inodes as _,
+			f_fsid: self.fs_id as _,
+			f_namelen: 255,
+			f_frsize: self.superblock.block_size() as _,
+			f_flags: if self.readonly { 1 } else { 0 },
+		})
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(ROOT_INODE)
+	}
+
+	fn get_inode(&mut self, io: &mut dyn IO, parent: Option<INode>, name: &String)
+		-> Result<INode, Errno> {
+		let parent = parent.unwrap_or(ROOT_INODE);
+		let node = self.read_inode(io, parent)?;
+
+		let mut found = None;
+		self.iter_dir(io, &node, |inode, _, entry_name| {
+			if found.is_none() && entry_name == name.as_bytes() {
+				found = Some(inode);
+			}
+			Ok(())
+		})?;
+
+		found.ok_or_else(|| errno!(ENOENT))
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		let node = self.read_inode(io, inode)?;
+
+		const S_IFMT: u16 = 0xf000;
+		const S_IFDIR: u16 = 0x4000;
+		const S_IFLNK: u16 = 0xa000;
+		let content = match node.mode & S_IFMT {
+			S_IFDIR => {
+				let mut entries = crate::util::container::hashmap::HashMap::new();
+				self.iter_dir(io, &node, |child_inode, file_type, child_name| {
+					entries.insert(String::try_from(child_name)?, crate::file::DirEntry {
+						inode: child_inode,
+						entry_type: file_type,
+					})?;
+					Ok(())
+				})?;
+				FileContent::Directory(entries)
+			}
+			S_IFLNK => {
+				let size = Self::inode_size(&node) as usize;
+				let mut buf = crate::vec![0u8; size]?;
+				self.read_inode_data(io, &node, 0, &mut buf)?;
+				FileContent::Link(String::try_from(buf.as_slice())?)
+			}
+			_ => FileContent::Regular,
+		};
+
+		let location = crate::file::FileLocation::Filesystem {
+			mountpoint_id: self.fs_id,
+			inode,
+		};
+		let mut file = File::new(name, node.uid as Uid, node.gid as Gid,
+			(node.mode & 0xfff) as Mode, location, content)?;
+		file.set_hard_links_count(node.links_count);
+		file.set_size(Self::inode_size(&node));
+
+		Ok(file)
+	}
+
+	fn add_file(&mut self, io: &mut dyn IO, parent_inode: INode, name: String, uid: Uid,
+		gid: Gid, mode: Mode, content: FileContent) -> Result<File, Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+		if name.as_bytes().len() > 255 {
+			return Err(errno!(ENAMETOOLONG));
+		}
+
+		const S_IFREG: u16 = 0x8000;
+		const S_IFDIR: u16 = 0x4000;
+		const S_IFLNK: u16 = 0xa000;
+		let (type_bits, raw_file_type) = match &content {
+			FileContent::Directory(_) => (S_IFDIR, 2u8),
+			FileContent::Link(_) => (S_IFLNK, 7u8),
+			_ => (S_IFREG, 1u8),
+		};
+
+		let inode = self.alloc_inode(io)?;
+		let mut node = DiskInode {
+			mode: type_bits | (mode & 0xfff) as u16,
+			uid: uid as u16,
+			size_lo: 0,
+			atime: 0,
+			ctime: 0,
+			mtime: 0,
+			dtime: 0,
+			gid: gid as u16,
+			links_count: 1,
+			blocks: 0,
+			flags: 0,
+			osd1: 0,
+			block: [0; DIRECT_BLOCKS + 3],
+			generation: 0,
+			file_acl: 0,
+			size_hi: 0,
+			faddr: 0,
+			osd2: [0; 12],
+		};
+
+		match &content {
+			FileContent::Directory(_) => {
+				// Every directory starts with `.` and `..`: the former claims a freshly
+				// allocated block whole, the latter is carved out of its slack like any other
+				// entry appended afterward.
+				self.write_raw_dir_entry(io, &mut node, 0, self.superblock.block_size() as u16,
+					b".", inode, 2)?;
+				self.append_dir_entry(io, &mut node, b"..", parent_inode, 2)?;
+				// The "." entry is a second reference to this inode, on top of the parent's
+				// entry for it added below.
+				node.links_count += 1;
+			}
+			FileContent::Link(target) => {
+				self.write_inode_data(io, &mut node, 0, target.as_bytes())?;
+			}
+			_ => {}
+		}
+
+		self.write_inode(io, inode, &node)?;
+
+		let mut parent = self.read_inode(io, parent_inode)?;
+		self.append_dir_entry(io, &mut parent, name.as_bytes(), inode, raw_file_type)?;
+		if raw_file_type == 2 {
+			// The new subdirectory's ".." is another reference to its parent.
+			parent.links_count += 1;
+		}
+		self.write_inode(io, parent_inode, &parent)?;
+
+		self.load_file(io, inode, name)
+	}
+
+	fn add_link(&mut self, io: &mut dyn IO, parent_inode: INode, name: &String, inode: INode)
+		-> Result<(), Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+		if name.as_bytes().len() > 255 {
+			return Err(errno!(ENAMETOOLONG));
+		}
+
+		const S_IFMT: u16 = 0xf000;
+		const S_IFDIR: u16 = 0x4000;
+		const S_IFLNK: u16 = 0xa000;
+		let mut target = self.read_inode(io, inode)?;
+		let raw_file_type = match target.mode & S_IFMT {
+			S_IFDIR => 2u8,
+			S_IFLNK => 7u8,
+			_ => 1u8,
+		};
+
+		let mut parent = self.read_inode(io, parent_inode)?;
+		self.append_dir_entry(io, &mut parent, name.as_bytes(), inode, raw_file_type)?;
+		self.write_inode(io, parent_inode, &parent)?;
+
+		target.links_count += 1;
+		self.write_inode(io, inode, &target)
+	}
+
+	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+
+		let inode = file.get_location().get_inode();
+		let mut node = self.read_inode(io, inode)?;
+
+		node.mode = (node.mode & 0xf000) | (file.get_mode() & 0xfff) as u16;
+		node.uid = file.get_uid() as u16;
+		node.gid = file.get_gid() as u16;
+
+		let size = file.get_size();
+		node.size_lo = size as u32;
+		node.size_hi = (size >> 32) as u32;
+
+		self.write_inode(io, inode, &node)
+	}
+
+	fn remove_file(&mut self, io: &mut dyn IO, parent_inode: INode, name: &String)
+		-> Result<(), Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+
+		let mut parent = self.read_inode(io, parent_inode)?;
+		let inode = self.remove_dir_entry(io, &mut parent, name.as_bytes())?;
+		self.write_inode(io, parent_inode, &parent)?;
+
+		let mut node = self.read_inode(io, inode)?;
+		if node.links_count > 0 {
+			node.links_count -= 1;
+		}
+
+		const S_IFMT: u16 = 0xf000;
+		const S_IFDIR: u16 = 0x4000;
+		if node.mode & S_IFMT == S_IFDIR {
+			// The removed directory's own ".." entry was another reference to its parent.
+			let mut parent = self.read_inode(io, parent_inode)?;
+			if parent.links_count > 0 {
+				parent.links_count -= 1;
+			}
+			self.write_inode(io, parent_inode, &parent)?;
+		}
+
+		if node.links_count == 0 {
+			self.free_inode_blocks(io, &node)?;
+			self.free_inode(io, inode)?;
+		} else {
+			self.write_inode(io, inode, &node)?;
+		}
+
+		Ok(())
+	}
+
+	fn read_node(&mut self, io: &mut dyn IO, inode: INode, off: u64, buf: &mut [u8])
+		-> Result<u64, Errno> {
+		let node = self.read_inode(io, inode)?;
+		self.read_inode_data(io, &node, off, buf)
+	}
+
+	fn write_node(&mut self, io: &mut dyn IO, inode: INode, off: u64, buf: &[u8])
+		-> Result<(), Errno> {
+		if self.readonly {
+			return Err(errno!(EROFS));
+		}
+
+		let mut node = self.read_inode(io, inode)?;
+		self.write_inode_data(io, &mut node, off, buf)?;
+		self.write_inode(io, inode, &node)
+	}
+}
+
+/// Structure representing the ext2 filesystem type.
+pub struct Ext2FsType {}
+
+impl FilesystemType for Ext2FsType {
+	fn get_name(&self) -> &[u8] {
+		b"ext2"
+	}
+
+	fn detect(&self, io: &mut dyn IO) -> Result<bool, Errno> {
+		match Ext2FS::read_superblock(io) {
+			Ok(superblock) => Ok(superblock.magic == EXT2_MAGIC),
+			Err(_) => Ok(false),
+		}
+	}
+
+	fn create_filesystem(&self, _io: &mut dyn IO, _fs_id: u32)
+		-> Result<SharedPtr<dyn Filesystem>, Errno> {
+		// ext2 filesystems are created with `mke2fs`, not by the kernel.
+		Err(errno!(EINVAL))
+	}
+
+	fn load_filesystem(&self, io: &mut dyn IO, fs_id: u32, mountpath: Path, readonly: bool)
+		-> Result<SharedPtr<dyn Filesystem>, Errno> {
+		Ok(SharedPtr::new(Ext2FS::mount(io, fs_id, readonly, mountpath)?)?)
+	}
+}
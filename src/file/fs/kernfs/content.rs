@@ -0,0 +1,76 @@
+//! Content returned by [`super::node::KernFSNode::get_content`].
+
+use crate::errno::Errno;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use core::borrow::Borrow;
+use core::ops::Deref;
+use core::ops::DerefMut;
+
+/// A node's content, as returned by [`super::node::KernFSNode::get_content`].
+///
+/// Most nodes simply expose the [`FileContent`] they store; a node backed by a
+/// [`ContentProvider`] instead builds one on the spot, reflecting live kernel state rather than a
+/// snapshot taken when the node was created. Either way, this derefs to `FileContent` so callers
+/// can inspect or pattern-match it the same way regardless of where it came from.
+pub enum NodeContent<'n> {
+	/// Content stored directly on the node.
+	Borrowed(&'n mut FileContent),
+	/// Content rendered on the spot by a [`ContentProvider`].
+	Owned(FileContent),
+}
+
+impl Deref for NodeContent<'_> {
+	type Target = FileContent;
+
+	fn deref(&self) -> &FileContent {
+		match self {
+			Self::Borrowed(content) => content,
+			Self::Owned(content) => content,
+		}
+	}
+}
+
+impl DerefMut for NodeContent<'_> {
+	fn deref_mut(&mut self) -> &mut FileContent {
+		match self {
+			Self::Borrowed(content) => content,
+			Self::Owned(content) => content,
+		}
+	}
+}
+
+impl Borrow<FileContent> for NodeContent<'_> {
+	fn borrow(&self) -> &FileContent {
+		self
+	}
+}
+
+/// Generates a node's content on demand instead of it being materialized ahead of time.
+///
+/// This backs virtual files whose content must reflect live kernel state at every read (uptime,
+/// meminfo, a process's `status`) and directories whose entries are computed rather than stored
+/// (a process's `fd` directory), so kernfs-based filesystems like procfs/sysfs can be layered
+/// directly on [`super::KernFS`] without pre-allocating or constantly rewriting node contents.
+pub trait ContentProvider {
+	/// Writes up to `buf.len()` bytes of the node's regular-file content starting at offset `off`
+	/// into `buf`, returning the number of bytes written and whether `off` plus that count
+	/// reached the end of the content.
+	///
+	/// The default implementation writes nothing, for providers backing a directory rather than
+	/// a regular file.
+	fn read(&self, off: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let _ = (off, buf);
+		Ok((0, true))
+	}
+
+	/// Enumerates the entries of a directory backed by this provider.
+	///
+	/// The default implementation returns an empty directory, for providers backing a regular
+	/// file rather than a directory.
+	fn entries(&self) -> Result<HashMap<String, DirEntry>, Errno> {
+		Ok(HashMap::new())
+	}
+}
@@ -2,6 +2,7 @@
 
 pub mod content;
 pub mod node;
+pub mod overlay;
 
 use crate::errno;
 use crate::errno::AllocError;
@@ -477,6 +478,13 @@ impl Filesystem for KernFS {
 		buf: &mut [u8],
 	) -> Result<u64, Errno> {
 		let node = self.get_node_mut(inode)?;
+
+		// A node backed by a content provider has its bytes generated fresh on every read,
+		// instead of being served out of a statically materialized content.
+		if let Some(provider) = node.get_provider() {
+			return Ok(provider.read(off, buf)?.0);
+		}
+
 		Ok(node.read(off, buf)?.0)
 	}
 
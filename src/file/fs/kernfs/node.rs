@@ -0,0 +1,302 @@
+//! Nodes of a [`super::KernFS`] filesystem.
+//!
+//! A node's metadata (mode, ownership, timestamps, link count) and its content are exposed
+//! through the [`KernFSNode`] trait; [`DummyKernFSNode`] is the generic implementation used by
+//! [`super::KernFS::add_file`] for plain files and directories, i.e. everything that isn't backed
+//! by a bespoke node type of its own.
+
+use super::content::ContentProvider;
+use super::content::NodeContent;
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::util::boxed::Box;
+use crate::util::container::vec::Vec;
+
+/// Trait representing a node of a [`super::KernFS`] filesystem.
+pub trait KernFSNode {
+	/// Returns the permissions of the node.
+	fn get_mode(&self) -> Mode;
+	/// Sets the permissions of the node.
+	fn set_mode(&mut self, mode: Mode);
+
+	/// Returns the id of the owner user.
+	fn get_uid(&self) -> Uid;
+	/// Sets the id of the owner user.
+	fn set_uid(&mut self, uid: Uid);
+
+	/// Returns the id of the owner group.
+	fn get_gid(&self) -> Gid;
+	/// Sets the id of the owner group.
+	fn set_gid(&mut self, gid: Gid);
+
+	/// Returns the number of hard links pointing to the node.
+	fn get_hard_links_count(&self) -> u16;
+	/// Sets the number of hard links pointing to the node.
+	fn set_hard_links_count(&mut self, count: u16);
+
+	/// Returns the size of the node's content, in bytes.
+	fn get_size(&self) -> u64;
+
+	/// Returns the timestamp of the last status change.
+	fn get_ctime(&self) -> u32;
+	/// Sets the timestamp of the last status change.
+	fn set_ctime(&mut self, ctime: u32);
+	/// Returns the timestamp of the last modification.
+	fn get_mtime(&self) -> u32;
+	/// Sets the timestamp of the last modification.
+	fn set_mtime(&mut self, mtime: u32);
+	/// Returns the timestamp of the last access.
+	fn get_atime(&self) -> u32;
+	/// Sets the timestamp of the last access.
+	fn set_atime(&mut self, atime: u32);
+
+	/// Returns the node's content.
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno>;
+
+	/// Reads data from the node's content at offset `off` into `buf`.
+	///
+	/// On success, the function returns the number of bytes read and whether the end of the
+	/// content has been reached.
+	fn read(&mut self, off: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno>;
+
+	/// Writes `buf` to the node's content at offset `off`.
+	fn write(&mut self, off: u64, buf: &[u8]) -> Result<(), Errno>;
+
+	/// Sets the node's content size to `size` bytes, dropping anything past it, or zero-filling up
+	/// to it if the content is shorter.
+	///
+	/// The default implementation returns `EINVAL`, for node types (procfs entries, symlinks...)
+	/// with no byte-addressable content to resize.
+	fn truncate(&mut self, _size: u64) -> Result<(), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	/// Returns the content provider backing this node, if any.
+	///
+	/// When present, [`super::KernFS::read_node`] calls its [`ContentProvider::read`] to
+	/// generate the requested bytes on demand instead of going through [`Self::read`]. The
+	/// default returns `None`, so a node that doesn't override it behaves exactly as before this
+	/// was added.
+	fn get_provider(&self) -> Option<&dyn ContentProvider> {
+		None
+	}
+}
+
+/// The default, generic implementation of [`KernFSNode`], used for plain files and directories
+/// that don't need a bespoke node type of their own.
+pub struct DummyKernFSNode {
+	/// Permissions of the file.
+	mode: Mode,
+	/// The id of the owner user.
+	uid: Uid,
+	/// The id of the owner group.
+	gid: Gid,
+
+	/// Timestamp of the last modification of the metadata.
+	ctime: u32,
+	/// Timestamp of the last modification of the file's content.
+	mtime: u32,
+	/// Timestamp of the last access to the file.
+	atime: u32,
+
+	/// The number of hard links pointing to this node.
+	hard_links_count: u16,
+
+	/// The content of the node.
+	content: FileContent,
+	/// Byte storage backing [`KernFSNode::read`]/[`KernFSNode::write`] for a `Regular` node.
+	/// Unused for directories and symlinks, whose payload lives in `content` itself.
+	data: Vec<u8>,
+
+	/// A provider rendering this node's content on demand, if any. When set, it takes priority
+	/// over `content`/`data` for reads and, for a directory, for entry enumeration.
+	provider: Option<Box<dyn ContentProvider>>,
+}
+
+impl DummyKernFSNode {
+	/// Creates a new node with statically-stored content.
+	pub fn new(mode: Mode, uid: Uid, gid: Gid, content: FileContent) -> Self {
+		Self {
+			mode,
+			uid,
+			gid,
+
+			ctime: 0,
+			mtime: 0,
+			atime: 0,
+
+			hard_links_count: 0,
+
+			content,
+			data: Vec::new(),
+
+			provider: None,
+		}
+	}
+
+	/// Creates a new node whose content is rendered on demand by `provider`, instead of being
+	/// stored statically.
+	///
+	/// `content` still records the node's type (`Regular` or `Directory`) so the rest of the
+	/// kernfs (permission checks, [`crate::file::FileType`] reporting, ...) keeps working
+	/// unchanged; only the actual bytes or directory entries are deferred to `provider`.
+	pub fn new_dynamic(
+		mode: Mode,
+		uid: Uid,
+		gid: Gid,
+		content: FileContent,
+		provider: Box<dyn ContentProvider>,
+	) -> Self {
+		Self {
+			mode,
+			uid,
+			gid,
+
+			ctime: 0,
+			mtime: 0,
+			atime: 0,
+
+			hard_links_count: 0,
+
+			content,
+			data: Vec::new(),
+
+			provider: Some(provider),
+		}
+	}
+}
+
+impl KernFSNode for DummyKernFSNode {
+	fn get_mode(&self) -> Mode {
+		self.mode
+	}
+
+	fn set_mode(&mut self, mode: Mode) {
+		self.mode = mode;
+	}
+
+	fn get_uid(&self) -> Uid {
+		self.uid
+	}
+
+	fn set_uid(&mut self, uid: Uid) {
+		self.uid = uid;
+	}
+
+	fn get_gid(&self) -> Gid {
+		self.gid
+	}
+
+	fn set_gid(&mut self, gid: Gid) {
+		self.gid = gid;
+	}
+
+	fn get_hard_links_count(&self) -> u16 {
+		self.hard_links_count
+	}
+
+	fn set_hard_links_count(&mut self, count: u16) {
+		self.hard_links_count = count;
+	}
+
+	fn get_size(&self) -> u64 {
+		self.data.len() as u64
+	}
+
+	fn get_ctime(&self) -> u32 {
+		self.ctime
+	}
+
+	fn set_ctime(&mut self, ctime: u32) {
+		self.ctime = ctime;
+	}
+
+	fn get_mtime(&self) -> u32 {
+		self.mtime
+	}
+
+	fn set_mtime(&mut self, mtime: u32) {
+		self.mtime = mtime;
+	}
+
+	fn get_atime(&self) -> u32 {
+		self.atime
+	}
+
+	fn set_atime(&mut self, atime: u32) {
+		self.atime = atime;
+	}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		let Some(provider) = &self.provider else {
+			return Ok(NodeContent::Borrowed(&mut self.content));
+		};
+
+		// Only a `Directory` node's entries are dynamic: a `Regular` node's bytes are served by
+		// `read`/`write` instead, and a `Link`'s target doesn't change.
+		match &self.content {
+			FileContent::Directory(_) => {
+				Ok(NodeContent::Owned(FileContent::Directory(provider.entries()?)))
+			}
+			_ => Ok(NodeContent::Borrowed(&mut self.content)),
+		}
+	}
+
+	fn read(&mut self, off: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if let Some(provider) = &self.provider {
+			return provider.read(off, buf);
+		}
+
+		if off >= self.data.len() as u64 {
+			return Ok((0, true));
+		}
+
+		let off = off as usize;
+		let len = core::cmp::min(buf.len(), self.data.len() - off);
+		buf[..len].copy_from_slice(&self.data[off..(off + len)]);
+
+		Ok((len as u64, off + len >= self.data.len()))
+	}
+
+	fn write(&mut self, off: u64, buf: &[u8]) -> Result<(), Errno> {
+		if self.provider.is_some() {
+			// Dynamically-generated content has no backing store to write through to.
+			return Err(errno!(EINVAL));
+		}
+
+		let off = off as usize;
+		let end = off + buf.len();
+		while self.data.len() < end {
+			self.data.push(0)?;
+		}
+		self.data[off..end].copy_from_slice(buf);
+
+		Ok(())
+	}
+
+	fn get_provider(&self) -> Option<&dyn ContentProvider> {
+		self.provider.as_deref()
+	}
+
+	fn truncate(&mut self, size: u64) -> Result<(), Errno> {
+		if self.provider.is_some() {
+			// Dynamically-generated content has no backing store to truncate.
+			return Err(errno!(EINVAL));
+		}
+
+		let size = size as usize;
+		if size < self.data.len() {
+			self.data.truncate(size);
+		} else {
+			while self.data.len() < size {
+				self.data.push(0)?;
+			}
+		}
+
+		Ok(())
+	}
+}
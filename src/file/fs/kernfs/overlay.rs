@@ -0,0 +1,585 @@
+//! Overlay/union mounts, stacking a writable [`KernFS`] layer over one or more read-only ones.
+//!
+//! [`OverlayFS`] keeps its own inode namespace, independent from the inodes used by the layers it
+//! stacks, and maps each of its inodes to where the corresponding node actually lives (see
+//! [`Origin`]). A name lookup merges the upper layer (which always shadows what's below it) with
+//! the lower layers; a mutation copies the affected node (and, recursively, every ancestor
+//! directory along its path) up into the upper layer before touching it, so the lower layers are
+//! never written to. Deleting a name that only exists below the upper layer leaves a whiteout
+//! marker recording that it must stay hidden.
+//!
+//! This only tracks a single parent per overlay inode, so a name created with [`OverlayFS`]'s
+//! `add_link` to an existing file correctly resolves to the same inode, but copy-up of that file will only
+//! recreate it at the path it was first resolved through.
+
+use super::node::DummyKernFSNode;
+use super::node::KernFSNode;
+use super::KernFS;
+use super::ROOT_INODE;
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::fs::Filesystem;
+use crate::file::fs::Statfs;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+
+/// Identifies one of the layers stacked by an [`OverlayFS`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Layer {
+	/// The single writable layer.
+	Upper,
+	/// A read-only layer, by index into [`OverlayFS::lowers`].
+	Lower(usize),
+}
+
+/// Where an overlay inode's node data actually lives.
+enum Origin {
+	/// A regular file, symlink, or other non-directory node: it exists in exactly one layer,
+	/// whichever one shadows the others for that name.
+	Leaf(Layer, INode),
+	/// A directory: its content is the merge of every layer that has an entry under the same
+	/// name, so it keeps gaining upper-layer entries via copy-up without losing access to
+	/// untouched lower-layer ones.
+	Dir {
+		/// This directory's node in the upper layer, once it has been created or copied up
+		/// there.
+		upper: Option<INode>,
+		/// This directory's node in each lower layer that has one, highest-priority first.
+		lowers: Vec<(usize, INode)>,
+	},
+}
+
+/// A filesystem that layers a writable upper [`KernFS`] over one or more read-only lower ones.
+pub struct OverlayFS {
+	/// The writable upper layer. Every mutation ends up here, via copy-up if necessary.
+	upper: KernFS,
+	/// The read-only lower layers, highest-priority first.
+	lowers: Vec<KernFS>,
+
+	/// Where each overlay inode's node data lives, indexed by overlay inode.
+	origins: Vec<Origin>,
+	/// Maps a `(layer, inode)` pair that has already been exposed through the overlay to the
+	/// overlay inode allocated for it, so repeated lookups of the same underlying node stay
+	/// stable.
+	resolved: Vec<(Layer, INode, INode)>,
+	/// The parent directory and name each overlay inode (other than the root) was first resolved
+	/// through, used to recreate its path in the upper layer on copy-up.
+	parents: HashMap<INode, (INode, String)>,
+	/// Names whited-out (deleted) in an upper-layer directory, keyed by that directory's
+	/// upper-layer inode, so a lower-layer entry of the same name doesn't reappear.
+	whiteouts: HashMap<INode, Vec<String>>,
+}
+
+impl OverlayFS {
+	/// Creates a new overlay stacking `upper` (writable) over `lowers` (read-only, highest
+	/// priority first).
+	///
+	/// Both `upper` and every layer of `lowers` must already have a root node set (see
+	/// [`KernFS::set_root`]).
+	pub fn new(upper: KernFS, lowers: Vec<KernFS>) -> Result<Self, Errno> {
+		let mut root_lowers = Vec::new();
+		for idx in 0..lowers.len() {
+			root_lowers.push((idx, ROOT_INODE))?;
+		}
+
+		let mut overlay = Self {
+			upper,
+			lowers,
+			origins: Vec::new(),
+			resolved: Vec::new(),
+			parents: HashMap::new(),
+			whiteouts: HashMap::new(),
+		};
+		overlay.origins.push(Origin::Dir {
+			upper: Some(ROOT_INODE),
+			lowers: root_lowers,
+		})?;
+		overlay.resolved.push((Layer::Upper, ROOT_INODE, ROOT_INODE))?;
+
+		Ok(overlay)
+	}
+
+	fn layer(&self, layer: Layer) -> &KernFS {
+		match layer {
+			Layer::Upper => &self.upper,
+			Layer::Lower(idx) => &self.lowers[idx],
+		}
+	}
+
+	fn layer_mut(&mut self, layer: Layer) -> &mut KernFS {
+		match layer {
+			Layer::Upper => &mut self.upper,
+			Layer::Lower(idx) => &mut self.lowers[idx],
+		}
+	}
+
+	fn find_resolved(&self, layer: Layer, inode: INode) -> Option<INode> {
+		self.resolved
+			.iter()
+			.find(|(l, i, _)| *l == layer && *i == inode)
+			.map(|(_, _, overlay_inode)| *overlay_inode)
+	}
+
+	/// Returns the layer a node's metadata (permissions, ownership, timestamps) should be read
+	/// from: the upper copy if it has one, otherwise the highest-priority lower copy.
+	fn representative(&self, inode: INode) -> Result<(Layer, INode), Errno> {
+		match self.origins.get(inode as usize).ok_or_else(|| errno!(ENOENT))? {
+			Origin::Leaf(layer, i) => Ok((*layer, *i)),
+			Origin::Dir { upper: Some(u), .. } => Ok((Layer::Upper, *u)),
+			Origin::Dir { lowers, .. } if !lowers.is_empty() => {
+				Ok((Layer::Lower(lowers[0].0), lowers[0].1))
+			}
+			Origin::Dir { .. } => Err(errno!(ENOENT)),
+		}
+	}
+
+	/// Returns the upper and lower sides of a directory's [`Origin`], copied out so they can be
+	/// used alongside further `&mut self` calls.
+	fn dir_sides(&self, dir: INode) -> Result<(Option<INode>, Vec<(usize, INode)>), Errno> {
+		let Origin::Dir { upper, lowers } = self.origins.get(dir as usize).ok_or_else(|| errno!(ENOENT))? else {
+			return Err(errno!(ENOTDIR));
+		};
+		let mut copy = Vec::new();
+		for &(idx, i) in lowers.iter() {
+			copy.push((idx, i))?;
+		}
+		Ok((*upper, copy))
+	}
+
+	/// Looks up `name` in the directory `dir` of `layer`, returning its inode and whether it is
+	/// itself a directory.
+	fn dir_entry(&mut self, layer: Layer, dir: INode, name: &[u8]) -> Result<Option<(INode, bool)>, Errno> {
+		let node = self.layer_mut(layer).get_node_mut(dir)?;
+		let content = node.get_content()?;
+		let FileContent::Directory(entries) = &*content else {
+			return Err(errno!(ENOTDIR));
+		};
+		Ok(entries
+			.get(name)
+			.map(|dirent| (dirent.inode, matches!(dirent.entry_type, FileType::Directory))))
+	}
+
+	fn is_whited_out(&self, upper_dir: INode, name: &[u8]) -> bool {
+		self.whiteouts
+			.get(&upper_dir)
+			.map(|names| names.iter().any(|n| n.as_bytes() == name))
+			.unwrap_or(false)
+	}
+
+	fn add_whiteout(&mut self, upper_dir: INode, name: &[u8]) -> Result<(), Errno> {
+		if let Some(names) = self.whiteouts.get_mut(&upper_dir) {
+			names.push(name.try_into()?)?;
+			return Ok(());
+		}
+		let mut names = Vec::new();
+		names.push(name.try_into()?)?;
+		self.whiteouts.insert(upper_dir, names)?;
+		Ok(())
+	}
+
+	fn clear_whiteout(&mut self, upper_dir: INode, name: &[u8]) {
+		if let Some(names) = self.whiteouts.get_mut(&upper_dir) {
+			if let Some(pos) = names.iter().position(|n| n.as_bytes() == name) {
+				names.remove(pos);
+			}
+		}
+	}
+
+	fn alloc_leaf(&mut self, parent: INode, name: &[u8], layer: Layer, inode: INode) -> Result<INode, Errno> {
+		if let Some(overlay_inode) = self.find_resolved(layer, inode) {
+			return Ok(overlay_inode);
+		}
+
+		let overlay_inode = self.origins.len() as INode;
+		self.origins.push(Origin::Leaf(layer, inode))?;
+		self.resolved.push((layer, inode, overlay_inode))?;
+		self.parents.insert(overlay_inode, (parent, name.try_into()?))?;
+
+		Ok(overlay_inode)
+	}
+
+	fn alloc_dir(&mut self, parent: INode, name: &[u8], dirs: Vec<(Layer, INode)>) -> Result<INode, Errno> {
+		let (primary_layer, primary_inode) = dirs[0];
+		if let Some(overlay_inode) = self.find_resolved(primary_layer, primary_inode) {
+			return Ok(overlay_inode);
+		}
+
+		let mut upper = None;
+		let mut lowers = Vec::new();
+		for &(layer, inode) in dirs.iter() {
+			match layer {
+				Layer::Upper => upper = Some(inode),
+				Layer::Lower(idx) => lowers.push((idx, inode))?,
+			}
+		}
+
+		let overlay_inode = self.origins.len() as INode;
+		self.origins.push(Origin::Dir {
+			upper,
+			lowers,
+		})?;
+		self.resolved.push((primary_layer, primary_inode, overlay_inode))?;
+		self.parents.insert(overlay_inode, (parent, name.try_into()?))?;
+
+		Ok(overlay_inode)
+	}
+
+	/// Resolves `name` in the directory `parent`, merging entries across layers.
+	///
+	/// A real entry in the upper layer always shadows everything below it; absent a real entry,
+	/// an upper-layer whiteout hides the name entirely. Otherwise, the lower layers are searched
+	/// in priority order: the first non-directory match wins outright, while directory matches
+	/// (from the upper layer and/or any number of lower layers) are merged together.
+	fn lookup(&mut self, parent: INode, name: &[u8]) -> Result<INode, Errno> {
+		let (upper, lowers) = self.dir_sides(parent)?;
+
+		let mut dirs = Vec::new();
+		if let Some(upper_dir) = upper {
+			match self.dir_entry(Layer::Upper, upper_dir, name)? {
+				Some((inode, true)) => dirs.push((Layer::Upper, inode))?,
+				Some((inode, false)) => return self.alloc_leaf(parent, name, Layer::Upper, inode),
+				None if self.is_whited_out(upper_dir, name) => return Err(errno!(ENOENT)),
+				None => {}
+			}
+		}
+
+		for (idx, lower_dir) in lowers {
+			let Some((inode, is_dir)) = self.dir_entry(Layer::Lower(idx), lower_dir, name)? else {
+				continue;
+			};
+			if is_dir {
+				dirs.push((Layer::Lower(idx), inode))?;
+			} else if dirs.is_empty() {
+				return self.alloc_leaf(parent, name, Layer::Lower(idx), inode);
+			}
+			// Otherwise, a non-directory entry underneath a directory that is already being
+			// merged is shadowed by it.
+		}
+
+		if dirs.is_empty() {
+			return Err(errno!(ENOENT));
+		}
+		self.alloc_dir(parent, name, dirs)
+	}
+
+	/// Builds the merged directory entries of the overlay directory `dir`, re-resolving each
+	/// name through [`Self::lookup`] so the returned inodes are proper overlay inodes.
+	fn merged_entries(&mut self, dir: INode) -> Result<HashMap<String, DirEntry>, Errno> {
+		let (upper, lowers) = self.dir_sides(dir)?;
+
+		let mut names = Vec::new();
+		let sides: Vec<(Layer, INode)> = {
+			let mut v = Vec::new();
+			if let Some(upper_dir) = upper {
+				v.push((Layer::Upper, upper_dir))?;
+			}
+			for &(idx, lower_dir) in &lowers {
+				v.push((Layer::Lower(idx), lower_dir))?;
+			}
+			v
+		};
+
+		for (layer, layer_dir) in sides {
+			let node = self.layer_mut(layer).get_node_mut(layer_dir)?;
+			let content = node.get_content()?;
+			let FileContent::Directory(entries) = &*content else {
+				continue;
+			};
+			for (name, _) in entries.iter() {
+				if name.as_bytes() == b"." || name.as_bytes() == b".." {
+					continue;
+				}
+				if !names.iter().any(|n: &String| n.as_bytes() == name.as_bytes()) {
+					names.push(name.try_clone()?)?;
+				}
+			}
+		}
+
+		let mut merged = HashMap::new();
+		merged.insert(b".".as_slice().try_into()?, DirEntry {
+			inode: dir,
+			entry_type: FileType::Directory,
+		})?;
+		merged.insert(b"..".as_slice().try_into()?, DirEntry {
+			inode: self.parents.get(&dir).map(|(p, _)| *p).unwrap_or(dir),
+			entry_type: FileType::Directory,
+		})?;
+		for name in names {
+			let Ok(child) = self.lookup(dir, name.as_bytes()) else {
+				continue;
+			};
+			let (layer, layer_inode) = self.representative(child)?;
+			let is_dir = matches!(self.origins[child as usize], Origin::Dir { .. });
+			let _ = (layer, layer_inode);
+			merged.insert(name, DirEntry {
+				inode: child,
+				entry_type: if is_dir { FileType::Directory } else { FileType::Regular },
+			})?;
+		}
+
+		Ok(merged)
+	}
+
+	/// Ensures the overlay inode `inode` has a writable copy in the upper layer, copying it (and
+	/// recursively, its ancestors) up from its lower layer if necessary, and returns its
+	/// upper-layer inode.
+	fn copy_up(&mut self, inode: INode) -> Result<INode, Errno> {
+		match self.origins.get(inode as usize).ok_or_else(|| errno!(ENOENT))? {
+			Origin::Leaf(Layer::Upper, upper_inode) => return Ok(*upper_inode),
+			Origin::Dir { upper: Some(upper_inode), .. } => return Ok(*upper_inode),
+			_ => {}
+		}
+
+		let (parent, name) = {
+			let (parent, name) = self.parents.get(&inode).ok_or_else(|| errno!(ENOENT))?;
+			(*parent, name.try_clone()?)
+		};
+		let upper_parent = self.copy_up(parent)?;
+
+		let is_dir = matches!(self.origins[inode as usize], Origin::Dir { .. });
+		let upper_inode = if is_dir {
+			let (layer, layer_inode) = self.representative(inode)?;
+			let node = self.layer(layer).get_node(layer_inode)?;
+			let (mode, uid, gid) = (node.get_mode(), node.get_uid(), node.get_gid());
+
+			let new_node = DummyKernFSNode::new(mode, uid, gid, FileContent::Directory(HashMap::new()));
+			let file = self.upper.add_file_inner(upper_parent, new_node, name)?;
+			file.get_location().get_inode()
+		} else {
+			let (layer, layer_inode) = self.representative(inode)?;
+			let (mode, uid, gid, content, size) = {
+				let node = self.layer_mut(layer).get_node_mut(layer_inode)?;
+				(node.get_mode(), node.get_uid(), node.get_gid(), node.get_content()?.to_owned()?, node.get_size())
+			};
+
+			let mut new_node = DummyKernFSNode::new(mode, uid, gid, content);
+			if size > 0 {
+				let mut data = crate::vec![0u8; size as usize]?;
+				self.layer_mut(layer).get_node_mut(layer_inode)?.read(0, &mut data)?;
+				new_node.write(0, &data)?;
+			}
+
+			let file = self.upper.add_file_inner(upper_parent, new_node, name)?;
+			file.get_location().get_inode()
+		};
+
+		match &mut self.origins[inode as usize] {
+			Origin::Leaf(layer, layer_inode) => {
+				*layer = Layer::Upper;
+				*layer_inode = upper_inode;
+			}
+			Origin::Dir { upper, .. } => *upper = Some(upper_inode),
+		}
+		self.resolved.push((Layer::Upper, upper_inode, inode))?;
+
+		Ok(upper_inode)
+	}
+}
+
+impl Filesystem for OverlayFS {
+	fn get_name(&self) -> &[u8] {
+		self.upper.get_name()
+	}
+
+	fn is_readonly(&self) -> bool {
+		// The overlay as a whole is only read-only if its upper layer has no room to write to;
+		// the lower layers being read-only is the entire point of the design.
+		self.upper.is_readonly()
+	}
+
+	fn must_cache(&self) -> bool {
+		false
+	}
+
+	fn get_stat(&self, io: &mut dyn IO) -> Result<Statfs, Errno> {
+		let mut stat = self.upper.get_stat(io)?;
+		for lower in &self.lowers {
+			stat.f_files += lower.get_stat(io)?.f_files;
+		}
+		Ok(stat)
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(ROOT_INODE)
+	}
+
+	fn get_inode(&mut self, _io: &mut dyn IO, parent: Option<INode>, name: &[u8]) -> Result<INode, Errno> {
+		let parent = parent.unwrap_or(ROOT_INODE);
+		self.lookup(parent, name)
+	}
+
+	fn load_file(&mut self, _: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		self.build_file(inode, name)
+	}
+
+	fn add_file(
+		&mut self,
+		_: &mut dyn IO,
+		parent_inode: INode,
+		name: String,
+		uid: Uid,
+		gid: Gid,
+		mode: Mode,
+		content: FileContent,
+	) -> Result<File, Errno> {
+		if self.upper.is_readonly() {
+			return Err(errno!(EROFS));
+		}
+
+		let upper_parent = self.copy_up(parent_inode)?;
+		let is_dir = matches!(content, FileContent::Directory(_));
+		let node = DummyKernFSNode::new(mode, uid, gid, content);
+		let file = self.upper.add_file_inner(upper_parent, node, name.try_clone()?)?;
+		let upper_inode = file.get_location().get_inode();
+
+		let overlay_inode = if is_dir {
+			let mut dirs = Vec::new();
+			dirs.push((Layer::Upper, upper_inode))?;
+			self.alloc_dir(parent_inode, name.as_bytes(), dirs)?
+		} else {
+			self.alloc_leaf(parent_inode, name.as_bytes(), Layer::Upper, upper_inode)?
+		};
+		self.clear_whiteout(upper_parent, name.as_bytes());
+
+		self.build_file(overlay_inode, name)
+	}
+
+	fn add_link(&mut self, io: &mut dyn IO, parent_inode: INode, name: &[u8], inode: INode) -> Result<(), Errno> {
+		if self.upper.is_readonly() {
+			return Err(errno!(EROFS));
+		}
+
+		let upper_parent = self.copy_up(parent_inode)?;
+		let upper_target = self.copy_up(inode)?;
+		self.upper.add_link(io, upper_parent, name, upper_target)?;
+		self.alloc_leaf(parent_inode, name, Layer::Upper, upper_target)?;
+		self.clear_whiteout(upper_parent, name);
+
+		Ok(())
+	}
+
+	fn update_inode(&mut self, _: &mut dyn IO, file: &File) -> Result<(), Errno> {
+		if self.upper.is_readonly() {
+			return Err(errno!(EROFS));
+		}
+
+		let inode = file.get_location().get_inode();
+		let upper_inode = self.copy_up(inode)?;
+		let node = self.upper.get_node_mut(upper_inode)?;
+		node.set_uid(file.get_uid());
+		node.set_gid(file.get_gid());
+		node.set_mode(file.get_mode());
+		node.set_ctime(file.ctime);
+		node.set_mtime(file.mtime);
+		node.set_atime(file.atime);
+
+		Ok(())
+	}
+
+	fn remove_file(&mut self, io: &mut dyn IO, parent_inode: INode, name: &[u8]) -> Result<u16, Errno> {
+		if self.upper.is_readonly() {
+			return Err(errno!(EROFS));
+		}
+
+		let (upper, lowers) = self.dir_sides(parent_inode)?;
+
+		let exists_upper = match upper {
+			Some(upper_dir) => self.dir_entry(Layer::Upper, upper_dir, name)?.is_some(),
+			None => false,
+		};
+		let mut exists_lower = false;
+		for (idx, lower_dir) in lowers {
+			if self.dir_entry(Layer::Lower(idx), lower_dir, name)?.is_some() {
+				exists_lower = true;
+				break;
+			}
+		}
+		if !exists_upper && !exists_lower {
+			return Err(errno!(ENOENT));
+		}
+
+		let mut links = 0;
+		if exists_upper {
+			links = self.upper.remove_file(io, upper.unwrap(), name)?;
+		}
+		if exists_lower {
+			let upper_dir = self.copy_up(parent_inode)?;
+			self.add_whiteout(upper_dir, name)?;
+		}
+
+		Ok(links)
+	}
+
+	fn read_node(&mut self, _: &mut dyn IO, inode: INode, off: u64, buf: &mut [u8]) -> Result<u64, Errno> {
+		let (layer, layer_inode) = self.representative(inode)?;
+		let node = self.layer_mut(layer).get_node_mut(layer_inode)?;
+
+		if let Some(provider) = node.get_provider() {
+			return Ok(provider.read(off, buf)?.0);
+		}
+		Ok(node.read(off, buf)?.0)
+	}
+
+	fn write_node(&mut self, _: &mut dyn IO, inode: INode, off: u64, buf: &[u8]) -> Result<(), Errno> {
+		if self.upper.is_readonly() {
+			return Err(errno!(EROFS));
+		}
+
+		let upper_inode = self.copy_up(inode)?;
+		self.upper.get_node_mut(upper_inode)?.write(off, buf)?;
+
+		Ok(())
+	}
+}
+
+impl OverlayFS {
+	/// Builds the `File` representing the overlay inode `inode`, named `name`; shared by
+	/// [`Filesystem::load_file`] and [`Filesystem::add_file`].
+	fn build_file(&mut self, inode: INode, name: String) -> Result<File, Errno> {
+		let (layer, layer_inode) = self.representative(inode)?;
+		let (mode, uid, gid, hard_links, size, ctime, mtime, atime) = {
+			let node = self.layer(layer).get_node(layer_inode)?;
+			(
+				node.get_mode(),
+				node.get_uid(),
+				node.get_gid(),
+				node.get_hard_links_count(),
+				node.get_size(),
+				node.get_ctime(),
+				node.get_mtime(),
+				node.get_atime(),
+			)
+		};
+
+		let is_dir = matches!(self.origins[inode as usize], Origin::Dir { .. });
+		let content = if is_dir {
+			FileContent::Directory(self.merged_entries(inode)?)
+		} else {
+			self.layer_mut(layer).get_node_mut(layer_inode)?.get_content()?.to_owned()?
+		};
+
+		let file_location = FileLocation::Filesystem {
+			mountpoint_id: 0,
+			inode,
+		};
+		let mut file = File::new(name, uid, gid, mode, file_location, content)?;
+		file.set_hard_links_count(hard_links);
+		file.set_size(size);
+		file.ctime = ctime;
+		file.mtime = mtime;
+		file.atime = atime;
+
+		Ok(file)
+	}
+}
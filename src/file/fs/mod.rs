@@ -0,0 +1,193 @@
+//! A filesystem is the means through which files are stored and organized on a storage device.
+//!
+//! This module implements the `Filesystem` trait, which is the interface every concrete
+//! filesystem implementation must satisfy so the VFS can operate on it generically.
+
+pub mod ext2;
+pub mod kernfs;
+pub mod procfs;
+pub mod tmp;
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::Gid;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::file::Uid;
+use crate::file::path::Path;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::ptr::SharedPtr;
+
+/// Structure representing the `statfs` syscall's result, describing a filesystem's usage.
+#[derive(Debug, Default)]
+pub struct Statfs {
+	/// Type of the filesystem.
+	pub f_type: u32,
+	/// Optimal transfer block size.
+	pub f_bsize: u32,
+	/// Total number of blocks in the filesystem.
+	pub f_blocks: u64,
+	/// Total number of free blocks.
+	pub f_bfree: u64,
+	/// Number of free blocks available to unprivileged users.
+	pub f_bavail: u64,
+	/// Total number of inodes.
+	pub f_files: u64,
+	/// Total number of free inodes.
+	pub f_ffree: u64,
+	/// Filesystem ID.
+	pub f_fsid: u64,
+	/// Maximum length of a file name.
+	pub f_namelen: u32,
+	/// Fragment size.
+	pub f_frsize: u32,
+	/// Mount flags.
+	pub f_flags: u32,
+}
+
+/// Trait representing a filesystem.
+pub trait Filesystem {
+	/// Returns the name of the filesystem.
+	fn get_name(&self) -> &[u8];
+	/// Returns the ID of the filesystem.
+	fn get_id(&self) -> u32;
+
+	/// Tells whether the filesystem is mounted in read-only.
+	fn is_readonly(&self) -> bool;
+	/// Tells whether the filesystem caches files in memory.
+	fn must_cache(&self) -> bool;
+
+	/// Returns statistics about the filesystem's usage.
+	fn get_stat(&self, io: &mut dyn IO) -> Result<Statfs, Errno>;
+
+	/// Returns the inode of the filesystem's root.
+	fn get_root_inode(&self, io: &mut dyn IO) -> Result<INode, Errno>;
+	/// Returns the inode of the entry named `name` in the directory `parent`.
+	///
+	/// If `parent` is `None`, the root of the filesystem is used.
+	fn get_inode(&mut self, io: &mut dyn IO, parent: Option<INode>, name: &String)
+		-> Result<INode, Errno>;
+
+	/// Loads the file with inode `inode` and name `name`.
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno>;
+
+	/// Adds a file to the filesystem.
+	fn add_file(&mut self, io: &mut dyn IO, parent_inode: INode, name: String, uid: Uid, gid: Gid,
+		mode: Mode, content: FileContent) -> Result<File, Errno>;
+	/// Adds a hard link pointing to `inode` named `name` in the directory `parent_inode`.
+	fn add_link(&mut self, io: &mut dyn IO, parent_inode: INode, name: &String, inode: INode)
+		-> Result<(), Errno>;
+	/// Updates the inode with the content of `file`.
+	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno>;
+	/// Removes the file named `name` from the directory `parent_inode`.
+	fn remove_file(&mut self, io: &mut dyn IO, parent_inode: INode, name: &String)
+		-> Result<(), Errno>;
+
+	/// Reads from the node `inode` at offset `off`, filling `buf`.
+	fn read_node(&mut self, io: &mut dyn IO, inode: INode, off: u64, buf: &mut [u8])
+		-> Result<u64, Errno>;
+	/// Writes to the node `inode` at offset `off` the content of `buf`.
+	fn write_node(&mut self, io: &mut dyn IO, inode: INode, off: u64, buf: &[u8])
+		-> Result<(), Errno>;
+
+	/// Writes the value of the extended attribute `name` on node `inode` into `buf`, returning the
+	/// value's size in bytes.
+	///
+	/// Arguments:
+	/// - `name` must start with the `user.`, `system.`, `trusted.` or `security.` namespace
+	///   prefix.
+	/// - `buf` receives the value. If empty, no data is written and the function only returns
+	///   the size required to hold the value, so the caller can size its buffer.
+	///
+	/// If the required size is greater than `buf`'s length (and `buf` isn't empty), the function
+	/// returns `ERANGE`. If the node has no such attribute, the function returns `ENODATA`.
+	///
+	/// The default implementation returns `ENOTSUP`, for filesystems with no xattr support.
+	fn get_xattr(&mut self, _io: &mut dyn IO, _inode: INode, _name: &String, _buf: &mut [u8])
+		-> Result<usize, Errno> {
+		Err(errno!(ENOTSUP))
+	}
+	/// Sets the extended attribute `name` on node `inode` to `value`.
+	///
+	/// `uid`/`gid` are the credentials of the calling process, used to enforce the namespace's
+	/// permission rules (the `security.` namespace requires privileges the default
+	/// implementation doesn't arbitrate; callers should perform LSM-specific checks separately).
+	fn set_xattr(&mut self, _io: &mut dyn IO, _inode: INode, _name: &String, _value: &[u8],
+		_uid: Uid, _gid: Gid) -> Result<(), Errno> {
+		Err(errno!(ENOTSUP))
+	}
+	/// Writes the `\0`-separated list of extended attribute names set on node `inode` into `buf`,
+	/// returning the number of bytes written (or required, if `buf` is empty).
+	fn list_xattr(&mut self, _io: &mut dyn IO, _inode: INode, _buf: &mut [u8])
+		-> Result<usize, Errno> {
+		Err(errno!(ENOTSUP))
+	}
+	/// Removes the extended attribute `name` from node `inode`.
+	fn remove_xattr(&mut self, _io: &mut dyn IO, _inode: INode, _name: &String, _uid: Uid,
+		_gid: Gid) -> Result<(), Errno> {
+		Err(errno!(ENOTSUP))
+	}
+
+	/// Preallocates or deallocates a range of a file, as described by the `fallocate` syscall.
+	///
+	/// `mode` is the bitwise OR of the `FALLOC_FL_*` flags; `0` requests plain preallocation.
+	///
+	/// The default implementation returns `ENOTSUP`, for filesystems with no preallocation
+	/// support.
+	fn fallocate(&mut self, _io: &mut dyn IO, _inode: INode, _mode: u32, _offset: u64,
+		_len: u64) -> Result<(), Errno> {
+		Err(errno!(ENOTSUP))
+	}
+}
+
+/// `fallocate`: deallocates (punches a hole in) the given range instead of allocating it. Must be
+/// used together with [`FALLOC_FL_KEEP_SIZE`].
+pub const FALLOC_FL_PUNCH_HOLE: u32 = 0x02;
+/// `fallocate`: do not change the apparent size of the file, even if the range extends past the
+/// current end.
+pub const FALLOC_FL_KEEP_SIZE: u32 = 0x01;
+/// `fallocate`: removes a range from the file without leaving a hole, shifting the data past it
+/// down by the size of the range.
+pub const FALLOC_FL_COLLAPSE_RANGE: u32 = 0x08;
+
+/// The `user.` namespace, usable by any process that can write to the file.
+pub const XATTR_NAMESPACE_USER: &[u8] = b"user.";
+/// The `system.` namespace, reserved for the kernel/filesystem implementation.
+pub const XATTR_NAMESPACE_SYSTEM: &[u8] = b"system.";
+/// The `security.` namespace, used by security modules; writing requires `CAP_SYS_ADMIN`.
+pub const XATTR_NAMESPACE_SECURITY: &[u8] = b"security.";
+/// The `trusted.` namespace, readable/writable only by privileged processes.
+pub const XATTR_NAMESPACE_TRUSTED: &[u8] = b"trusted.";
+
+/// Tells whether `uid` is allowed to set or remove the extended attribute `name`.
+pub fn can_write_xattr(name: &[u8], uid: Uid) -> bool {
+	if name.starts_with(XATTR_NAMESPACE_SECURITY) || name.starts_with(XATTR_NAMESPACE_TRUSTED) {
+		return uid == crate::file::ROOT_UID;
+	}
+	if name.starts_with(XATTR_NAMESPACE_USER) || name.starts_with(XATTR_NAMESPACE_SYSTEM) {
+		return true;
+	}
+
+	// Unknown namespace
+	false
+}
+
+/// Trait representing a filesystem type, used to instantiate filesystems.
+pub trait FilesystemType {
+	/// Returns the name of the filesystem.
+	fn get_name(&self) -> &[u8];
+
+	/// Tells whether the filesystem corresponding to this type can be found on the given IO
+	/// interface.
+	fn detect(&self, io: &mut dyn IO) -> Result<bool, Errno>;
+
+	/// Creates a new instance of the filesystem.
+	fn create_filesystem(&self, io: &mut dyn IO, fs_id: u32)
+		-> Result<SharedPtr<dyn Filesystem>, Errno>;
+	/// Loads the filesystem from the given IO interface.
+	fn load_filesystem(&self, io: &mut dyn IO, fs_id: u32, mountpath: Path, readonly: bool)
+		-> Result<SharedPtr<dyn Filesystem>, Errno>;
+}
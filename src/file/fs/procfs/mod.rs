@@ -1,6 +1,7 @@
 //! The procfs is a virtual filesystem which provides informations about processes.
 
 pub mod mount;
+pub mod proc_dir;
 
 use crate::errno::Errno;
 use crate::file::DirEntry;
@@ -14,20 +15,100 @@ use crate::file::Uid;
 use crate::file::fs::Statfs;
 use crate::file::path::Path;
 use crate::process::pid::Pid;
+use crate::process::Process;
 use crate::util::IO;
+use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::ptr::SharedPtr;
 use super::Filesystem;
 use super::FilesystemType;
-use super::kernfs::KernFS;
+use super::kernfs::content::NodeContent;
+use super::kernfs::node::DummyKernFSNode;
 use super::kernfs::node::KernFSNode;
+use super::kernfs::KernFS;
+use super::kernfs::ROOT_INODE;
+use proc_dir::ProcDir;
+
+/// The `self` link, which resolves to the directory of the process currently making the call,
+/// mirroring Linux's `/proc/self`.
+struct SelfLink {}
+
+impl KernFSNode for SelfLink {
+	fn get_mode(&self) -> Mode {
+		0o777
+	}
+
+	fn set_mode(&mut self, _mode: Mode) {}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn set_uid(&mut self, _uid: Uid) {}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn set_gid(&mut self, _gid: Gid) {}
+
+	fn get_hard_links_count(&self) -> u16 {
+		1
+	}
+
+	fn set_hard_links_count(&mut self, _count: u16) {}
+
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn get_ctime(&self) -> u32 {
+		0
+	}
+
+	fn set_ctime(&mut self, _ctime: u32) {}
+
+	fn get_mtime(&self) -> u32 {
+		0
+	}
+
+	fn set_mtime(&mut self, _mtime: u32) {}
+
+	fn get_atime(&self) -> u32 {
+		0
+	}
+
+	fn set_atime(&mut self, _atime: u32) {}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		let proc_mutex = Process::get_current().ok_or_else(|| errno!(ESRCH))?;
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		let s = crate::format!("{}", proc.get_pid())?;
+		Ok(NodeContent::Owned(FileContent::Link(s)))
+	}
+
+	fn read(&mut self, _off: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<(), Errno> {
+		Err(errno!(EINVAL))
+	}
+}
 
 /// Structure representing the procfs.
 /// On the inside, the procfs works using a kernfs.
 pub struct ProcFS {
 	/// The kernfs.
 	fs: KernFS,
+	/// The ID of the mounted filesystem.
+	fs_id: u32,
+
+	/// The per-PID directories currently exposed, keyed by PID.
+	processes: HashMap<Pid, ProcDir>,
 }
 
 impl ProcFS {
@@ -35,41 +116,75 @@ impl ProcFS {
 	/// `readonly` tells whether the filesystem is readonly.
 	/// `fs_id` is the ID of the mounted filesystem.
 	/// `mountpath` is the path at which the filesystem is mounted.
-	pub fn new(readonly: bool, fs_id: u32, mountpath: Path) -> Result<Self, Errno> {
+	pub fn new(readonly: bool, fs_id: u32, _mountpath: Path) -> Result<Self, Errno> {
 		let mut fs = Self {
-			fs: KernFS::new(String::from(b"procfs")?, fs_id, readonly, mountpath)?,
+			fs: KernFS::new(String::from(b"procfs")?, readonly)?,
+			fs_id,
+
+			processes: HashMap::new(),
 		};
 
 		let mut root_entries = HashMap::new();
 
 		// Creating /proc/mounts
-		let mount_inode = fs.fs.add_node(KernFSNode::new(0o444, 0, 0,
-			FileContent::Link(String::from(b"self/mounts")?), None))?;
+		let mount_node = DummyKernFSNode::new(0o444, 0, 0,
+			FileContent::Link(String::from(b"self/mounts")?));
+		let mount_inode = fs.fs.add_node(Box::new(mount_node)?)?;
 		root_entries.insert(String::from(b"mounts")?, DirEntry {
 			inode: mount_inode,
 			entry_type: FileType::Link,
 		})?;
 
-		// TODO Create the `self` link (value depends on the current process)
+		// Creating /proc/self
+		let self_inode = fs.fs.add_node(Box::new(SelfLink {})?)?;
+		root_entries.insert(String::from(b"self")?, DirEntry {
+			inode: self_inode,
+			entry_type: FileType::Link,
+		})?;
 
 		// Adding the root node
-		let root_node = KernFSNode::new(0o555, 0, 0, FileContent::Directory(root_entries), None);
-		fs.fs.set_root(root_node)?;
+		let root_node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(root_entries));
+		fs.fs.set_root(Box::new(root_node)?)?;
 
 		Ok(fs)
 	}
 
 	/// Adds a process with the given PID `pid` to the filesystem.
-	pub fn add_process(&mut self, _pid: Pid) -> Result<(), Errno> {
-		// TODO
-		todo!();
+	pub fn add_process(&mut self, pid: Pid) -> Result<(), Errno> {
+		let dir = proc_dir::build(&mut self.fs, pid)?;
+
+		let name = crate::format!("{pid}")?;
+		let root = self.fs.get_node_mut(ROOT_INODE)?;
+		let mut content = root.get_content()?;
+		let FileContent::Directory(entries) = &mut *content else {
+			return Err(errno!(ENOTDIR));
+		};
+		entries.insert(name, DirEntry {
+			inode: dir.inode,
+			entry_type: FileType::Directory,
+		})?;
+
+		self.processes.insert(pid, dir)?;
+
+		Ok(())
 	}
 
 	/// Removes the process with pid `pid` from the filesystem.
 	/// If the process doesn't exist, the function does nothing.
-	pub fn remove_process(&mut self, _pid: Pid) -> Result<(), Errno> {
-		// TODO
-		todo!();
+	pub fn remove_process(&mut self, pid: Pid) -> Result<(), Errno> {
+		let Some(dir) = self.processes.remove(&pid) else {
+			return Ok(());
+		};
+
+		let name = crate::format!("{pid}")?;
+		let root = self.fs.get_node_mut(ROOT_INODE)?;
+		let mut content = root.get_content()?;
+		if let FileContent::Directory(entries) = &mut *content {
+			entries.remove(name.as_bytes());
+		}
+		drop(content);
+
+		proc_dir::destroy(&mut self.fs, &dir)
 	}
 }
 
@@ -79,7 +194,7 @@ impl Filesystem for ProcFS {
 	}
 
 	fn get_id(&self) -> u32 {
-		self.fs.get_id()
+		self.fs_id
 	}
 
 	fn is_readonly(&self) -> bool {
@@ -151,11 +266,15 @@ impl FilesystemType for ProcFsType {
 
 	fn create_filesystem(&self, _io: &mut dyn IO, fs_id: u32)
 		-> Result<SharedPtr<dyn Filesystem>, Errno> {
-		Ok(SharedPtr::new(ProcFS::new(false, fs_id, Path::root())?)?)
+		let fs = SharedPtr::new(ProcFS::new(false, fs_id, Path::root())?)?;
+		mount::register(fs.clone());
+		Ok(fs)
 	}
 
 	fn load_filesystem(&self, _io: &mut dyn IO, fs_id: u32, mountpath: Path, readonly: bool)
 		-> Result<SharedPtr<dyn Filesystem>, Errno> {
-		Ok(SharedPtr::new(ProcFS::new(readonly, fs_id, mountpath)?)?)
+		let fs = SharedPtr::new(ProcFS::new(readonly, fs_id, mountpath)?)?;
+		mount::register(fs.clone());
+		Ok(fs)
 	}
 }
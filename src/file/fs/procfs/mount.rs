@@ -0,0 +1,57 @@
+//! Tracks the currently mounted procfs instance, if any.
+//!
+//! Process lifecycle events (creation, exit) happen in the scheduler, which has no reason to know
+//! about mountpoints or filesystem IDs. This module lets it notify procfs of those events by PID
+//! alone, without needing to look the filesystem up through the generic mountpoint machinery.
+
+use crate::errno::Errno;
+use crate::process::pid::Pid;
+use crate::util::lock::Mutex;
+use crate::util::ptr::SharedPtr;
+use super::Filesystem;
+use super::ProcFS;
+
+/// The currently mounted procfs instance, set by `ProcFsType::create_filesystem`/
+/// `load_filesystem` and cleared when it is unmounted.
+///
+/// This kernel only ever mounts a single procfs instance at `/proc`, so a single slot is enough.
+static MOUNTED: Mutex<Option<SharedPtr<ProcFS>>> = Mutex::new(None);
+
+/// Registers `fs` as the currently mounted procfs instance.
+pub(super) fn register(fs: SharedPtr<ProcFS>) {
+	*MOUNTED.lock().get_mut() = Some(fs);
+}
+
+/// Clears the currently mounted procfs instance, if `fs_id` is the one currently registered.
+pub fn unregister(fs_id: u32) {
+	let mut guard = MOUNTED.lock();
+	if guard.get().as_ref().is_some_and(|fs| fs.lock().get().get_id() == fs_id) {
+		*guard.get_mut() = None;
+	}
+}
+
+/// Adds the process with PID `pid` to the currently mounted procfs instance.
+///
+/// Does nothing if no procfs is mounted.
+pub fn add_process(pid: Pid) -> Result<(), Errno> {
+	let fs = MOUNTED.lock().get().clone();
+	let Some(fs) = fs else {
+		return Ok(());
+	};
+
+	fs.lock().get_mut().add_process(pid)
+}
+
+/// Removes the process with PID `pid` from the currently mounted procfs instance.
+///
+/// Does nothing if no procfs is mounted.
+pub fn remove_process(pid: Pid) {
+	let fs = MOUNTED.lock().get().clone();
+	let Some(fs) = fs else {
+		return;
+	};
+
+	// The process is exiting either way; there is nothing more to do if its procfs entry can't
+	// be torn down cleanly.
+	let _ = fs.lock().get_mut().remove_process(pid);
+}
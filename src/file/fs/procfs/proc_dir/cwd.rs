@@ -0,0 +1,102 @@
+//! This module implements the `cwd` node, which is a link to the current working directory of
+//! the process.
+
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::NodeContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Gid;
+use crate::file::Mode;
+use crate::file::ROOT_GID;
+use crate::file::ROOT_UID;
+use crate::file::Uid;
+use crate::process::oom;
+use crate::process::pid::Pid;
+use crate::process::Process;
+
+/// Structure representing the `cwd` node.
+pub struct Cwd {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for Cwd {
+	fn get_mode(&self) -> Mode {
+		0o777
+	}
+
+	fn set_mode(&mut self, _mode: Mode) {}
+
+	fn get_uid(&self) -> Uid {
+		// A node can outlive the process it describes (e.g. a concurrent exit racing a stat()),
+		// in which case there is no real owner to report; fall back to root rather than panicking.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_UID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_euid()
+	}
+
+	fn set_uid(&mut self, _uid: Uid) {}
+
+	fn get_gid(&self) -> Gid {
+		// Same rationale as `get_uid`: a torn-down process has no real owner, so fall back to
+		// root.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_GID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_egid()
+	}
+
+	fn set_gid(&mut self, _gid: Gid) {}
+
+	fn get_hard_links_count(&self) -> u16 {
+		1
+	}
+
+	fn set_hard_links_count(&mut self, _count: u16) {}
+
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn get_ctime(&self) -> u32 {
+		0
+	}
+
+	fn set_ctime(&mut self, _ctime: u32) {}
+
+	fn get_mtime(&self) -> u32 {
+		0
+	}
+
+	fn set_mtime(&mut self, _mtime: u32) {}
+
+	fn get_atime(&self) -> u32 {
+		0
+	}
+
+	fn set_atime(&mut self, _atime: u32) {}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ESRCH))?;
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		let s = oom::wrap(|| proc.get_cwd().as_string());
+		Ok(NodeContent::Owned(FileContent::Link(s)))
+	}
+
+	fn read(&mut self, _off: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<(), Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -0,0 +1,250 @@
+//! This module implements the `fd` directory, which lists the process's open file descriptors as
+//! symlinks to the files they point to.
+
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::NodeContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::Gid;
+use crate::file::Mode;
+use crate::file::ROOT_GID;
+use crate::file::ROOT_UID;
+use crate::file::Uid;
+use crate::file::INode;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::container::hashmap::HashMap;
+
+/// The highest file descriptor number listed in a `fd` directory.
+///
+/// Kernfs directory entries are resolved through statically-allocated nodes, so the directory
+/// can't grow or shrink one node at a time as descriptors are opened and closed; instead, one
+/// [`FdLink`] node is pre-allocated per slot in this range, and [`Fd::get_content`] only lists the
+/// slots that currently hold an open descriptor.
+///
+/// Matches Linux's default soft `RLIMIT_NOFILE` (1024), so any descriptor a process can open
+/// under the common default limit is listed. A process raising its own limit past this (via
+/// `setrlimit`, which this kernel doesn't implement yet) would silently lose visibility into fds
+/// numbered above it; bump this constant in step if/when that lands.
+pub(super) const FD_DIR_MAX: u32 = 1024;
+
+/// Structure representing a single entry of a `fd` directory, linking to the file open on
+/// descriptor `fd` of the process `pid`.
+pub struct FdLink {
+	/// The PID of the process.
+	pub pid: Pid,
+	/// The file descriptor.
+	pub fd: u32,
+}
+
+impl KernFSNode for FdLink {
+	fn get_mode(&self) -> Mode {
+		0o777
+	}
+
+	fn set_mode(&mut self, _mode: Mode) {}
+
+	fn get_uid(&self) -> Uid {
+		// A node can outlive the process it describes (e.g. a concurrent exit racing a stat()),
+		// in which case there is no real owner to report; fall back to root rather than panicking.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_UID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_euid()
+	}
+
+	fn set_uid(&mut self, _uid: Uid) {}
+
+	fn get_gid(&self) -> Gid {
+		// Same rationale as `get_uid`: a torn-down process has no real owner, so fall back to
+		// root.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_GID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_egid()
+	}
+
+	fn set_gid(&mut self, _gid: Gid) {}
+
+	fn get_hard_links_count(&self) -> u16 {
+		1
+	}
+
+	fn set_hard_links_count(&mut self, _count: u16) {}
+
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn get_ctime(&self) -> u32 {
+		0
+	}
+
+	fn set_ctime(&mut self, _ctime: u32) {}
+
+	fn get_mtime(&self) -> u32 {
+		0
+	}
+
+	fn set_mtime(&mut self, _mtime: u32) {}
+
+	fn get_atime(&self) -> u32 {
+		0
+	}
+
+	fn set_atime(&mut self, _atime: u32) {}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ESRCH))?;
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		let fds_mutex = proc.get_fds().ok_or_else(|| errno!(ESRCH))?;
+		let fds = fds_mutex.lock();
+		let open_fd = fds.get_fd(self.fd).ok_or_else(|| errno!(ENOENT))?;
+		let open_file_mutex = open_fd.get_open_file()?;
+		let open_file = open_file_mutex.lock();
+
+		// Real filesystem paths can't always be recovered from a bare file location (pipes,
+		// sockets, anonymous memory objects...), so fall back to a synthetic descriptor like
+		// Linux does for the same cases.
+		let target = match open_file.get_location() {
+			FileLocation::Filesystem { mountpoint_id, inode } => {
+				crate::format!("[filesystem:{mountpoint_id}:{inode}]")?
+			}
+		};
+
+		Ok(NodeContent::Owned(FileContent::Link(target)))
+	}
+
+	fn read(&mut self, _off: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<(), Errno> {
+		Err(errno!(EINVAL))
+	}
+}
+
+/// Structure representing the `fd` directory node.
+pub struct Fd {
+	/// The PID of the process.
+	pub pid: Pid,
+	/// The inode of each pre-allocated [`FdLink`] slot, indexed by file descriptor number.
+	///
+	/// Populated once by [`super::build`] right after each slot is allocated, since kernfs
+	/// directory entries must name a concrete, already-existing inode; the set of descriptors
+	/// that are actually open is instead re-checked on every [`Self::get_content`] call.
+	pub fd_inodes: HashMap<u32, INode>,
+}
+
+impl KernFSNode for Fd {
+	fn get_mode(&self) -> Mode {
+		0o555
+	}
+
+	fn set_mode(&mut self, _mode: Mode) {}
+
+	fn get_uid(&self) -> Uid {
+		// A node can outlive the process it describes (e.g. a concurrent exit racing a stat()),
+		// in which case there is no real owner to report; fall back to root rather than panicking.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_UID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_euid()
+	}
+
+	fn set_uid(&mut self, _uid: Uid) {}
+
+	fn get_gid(&self) -> Gid {
+		// Same rationale as `get_uid`: a torn-down process has no real owner, so fall back to
+		// root.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_GID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_egid()
+	}
+
+	fn set_gid(&mut self, _gid: Gid) {}
+
+	fn get_hard_links_count(&self) -> u16 {
+		2
+	}
+
+	fn set_hard_links_count(&mut self, _count: u16) {}
+
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn get_ctime(&self) -> u32 {
+		0
+	}
+
+	fn set_ctime(&mut self, _ctime: u32) {}
+
+	fn get_mtime(&self) -> u32 {
+		0
+	}
+
+	fn set_mtime(&mut self, _mtime: u32) {}
+
+	fn get_atime(&self) -> u32 {
+		0
+	}
+
+	fn set_atime(&mut self, _atime: u32) {}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ESRCH))?;
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		let fds_mutex = proc.get_fds().ok_or_else(|| errno!(ESRCH))?;
+		let fds = fds_mutex.lock();
+
+		let mut entries = HashMap::new();
+		for fd in 0..FD_DIR_MAX {
+			if fds.get_fd(fd).is_none() {
+				continue;
+			}
+			let Some(&inode) = self.fd_inodes.get(&fd) else {
+				continue;
+			};
+
+			let name = crate::format!("{fd}")?;
+			entries.insert(
+				name,
+				DirEntry {
+					inode,
+					entry_type: FileType::Link,
+				},
+			)?;
+		}
+
+		Ok(NodeContent::Owned(FileContent::Directory(entries)))
+	}
+
+	fn read(&mut self, _off: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<(), Errno> {
+		Err(errno!(EINVAL))
+	}
+}
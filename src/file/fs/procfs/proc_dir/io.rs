@@ -0,0 +1,135 @@
+//! This module implements the `io` node, which reports the process's I/O accounting, mirroring
+//! Linux's `/proc/<pid>/io`.
+//!
+//! The counters themselves (`rchar`/`wchar`/`syscr`/`syscw`/`read_bytes`/`write_bytes`/
+//! `cancelled_write_bytes`) are expected to live on `Process`, incremented from the read/write
+//! syscall paths; those paths aren't part of this tree, so the counters below always read back as
+//! whatever `Process` reports (zero until that instrumentation lands).
+
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::NodeContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Gid;
+use crate::file::Mode;
+use crate::file::ROOT_GID;
+use crate::file::ROOT_UID;
+use crate::file::Uid;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use core::cmp::min;
+
+/// Structure representing the `io` node.
+pub struct Io {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for Io {
+	fn get_mode(&self) -> Mode {
+		0o400
+	}
+
+	fn set_mode(&mut self, _mode: Mode) {}
+
+	fn get_uid(&self) -> Uid {
+		// A node can outlive the process it describes (e.g. a concurrent exit racing a stat()),
+		// in which case there is no real owner to report; fall back to root rather than panicking.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_UID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_euid()
+	}
+
+	fn set_uid(&mut self, _uid: Uid) {}
+
+	fn get_gid(&self) -> Gid {
+		// Same rationale as `get_uid`: a torn-down process has no real owner, so fall back to
+		// root.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_GID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_egid()
+	}
+
+	fn set_gid(&mut self, _gid: Gid) {}
+
+	fn get_hard_links_count(&self) -> u16 {
+		1
+	}
+
+	fn set_hard_links_count(&mut self, _count: u16) {}
+
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn get_ctime(&self) -> u32 {
+		0
+	}
+
+	fn set_ctime(&mut self, _ctime: u32) {}
+
+	fn get_mtime(&self) -> u32 {
+		0
+	}
+
+	fn set_mtime(&mut self, _mtime: u32) {}
+
+	fn get_atime(&self) -> u32 {
+		0
+	}
+
+	fn set_atime(&mut self, _atime: u32) {}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		Ok(NodeContent::Owned(FileContent::Regular))
+	}
+
+	fn read(&mut self, offset: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buf.is_empty() {
+			return Ok((0, false));
+		}
+
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ESRCH))?;
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		let rchar = proc.get_rchar();
+		let wchar = proc.get_wchar();
+		let syscr = proc.get_syscr();
+		let syscw = proc.get_syscw();
+		let read_bytes = proc.get_read_bytes();
+		let write_bytes = proc.get_write_bytes();
+		let cancelled_write_bytes = proc.get_cancelled_write_bytes();
+
+		let content = crate::format!("rchar: {rchar}
+wchar: {wchar}
+syscr: {syscr}
+syscw: {syscw}
+read_bytes: {read_bytes}
+write_bytes: {write_bytes}
+cancelled_write_bytes: {cancelled_write_bytes}
+")?;
+
+		let content_bytes = content.as_bytes();
+		if offset >= content_bytes.len() as u64 {
+			return Ok((0, true));
+		}
+		let len = min((content_bytes.len() as u64 - offset) as usize, buf.len());
+		buf[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<(), Errno> {
+		Err(errno!(EINVAL))
+	}
+}
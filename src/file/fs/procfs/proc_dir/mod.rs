@@ -0,0 +1,176 @@
+//! The per-PID directory exposed by the procfs, containing informations about a single process.
+
+pub mod cmdline;
+pub mod cwd;
+pub mod exe;
+pub mod fd;
+pub mod io;
+pub mod maps;
+pub mod root;
+pub mod stat;
+pub mod status;
+pub mod task;
+
+use crate::errno::Errno;
+use crate::file::fs::kernfs::node::DummyKernFSNode;
+use crate::file::fs::kernfs::KernFS;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::process::pid::Pid;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use fd::Fd;
+use fd::FdLink;
+use fd::FD_DIR_MAX;
+use task::TaskLink;
+
+/// Every node allocated for a single `/proc/<pid>` directory, in the order needed to tear it back
+/// down: the directory's own children, then the directory itself.
+pub struct ProcDir {
+	/// The inode of the `/proc/<pid>` directory itself.
+	pub inode: INode,
+	/// The inodes of every node owned by this directory (including the nested `fd/*` slots, but
+	/// not the directory's own inode).
+	children: Vec<INode>,
+}
+
+/// Builds the `/proc/<pid>` directory and all of its entries.
+pub fn build(fs: &mut KernFS, pid: Pid) -> Result<ProcDir, Errno> {
+	let mut entries = HashMap::new();
+	let mut children = Vec::new();
+
+	let exe_inode = fs.add_node(Box::new(exe::Exe {
+		pid,
+	})?)?;
+	children.push(exe_inode)?;
+	entries.insert(String::from(b"exe")?, DirEntry {
+		inode: exe_inode,
+		entry_type: FileType::Link,
+	})?;
+
+	let status_inode = fs.add_node(Box::new(status::Status {
+		pid,
+	})?)?;
+	children.push(status_inode)?;
+	entries.insert(String::from(b"status")?, DirEntry {
+		inode: status_inode,
+		entry_type: FileType::Regular,
+	})?;
+
+	let cmdline_inode = fs.add_node(Box::new(cmdline::Cmdline {
+		pid,
+	})?)?;
+	children.push(cmdline_inode)?;
+	entries.insert(String::from(b"cmdline")?, DirEntry {
+		inode: cmdline_inode,
+		entry_type: FileType::Regular,
+	})?;
+
+	let stat_inode = fs.add_node(Box::new(stat::Stat {
+		pid,
+	})?)?;
+	children.push(stat_inode)?;
+	entries.insert(String::from(b"stat")?, DirEntry {
+		inode: stat_inode,
+		entry_type: FileType::Regular,
+	})?;
+
+	let cwd_inode = fs.add_node(Box::new(cwd::Cwd {
+		pid,
+	})?)?;
+	children.push(cwd_inode)?;
+	entries.insert(String::from(b"cwd")?, DirEntry {
+		inode: cwd_inode,
+		entry_type: FileType::Link,
+	})?;
+
+	let root_inode = fs.add_node(Box::new(root::Root {
+		pid,
+	})?)?;
+	children.push(root_inode)?;
+	entries.insert(String::from(b"root")?, DirEntry {
+		inode: root_inode,
+		entry_type: FileType::Link,
+	})?;
+
+	let maps_inode = fs.add_node(Box::new(maps::Maps {
+		pid,
+	})?)?;
+	children.push(maps_inode)?;
+	entries.insert(String::from(b"maps")?, DirEntry {
+		inode: maps_inode,
+		entry_type: FileType::Regular,
+	})?;
+
+	let io_inode = fs.add_node(Box::new(io::Io {
+		pid,
+	})?)?;
+	children.push(io_inode)?;
+	entries.insert(String::from(b"io")?, DirEntry {
+		inode: io_inode,
+		entry_type: FileType::Regular,
+	})?;
+
+	// This kernel doesn't implement `clone`-created threads yet, so a thread group always
+	// contains exactly the process owning it; once real threads exist, each one should get its
+	// own `TaskLink` entry here instead of just the group leader's.
+	let mut task_entries = HashMap::new();
+	let task_self_inode = fs.add_node(Box::new(TaskLink {
+		pid,
+		tid: pid,
+	})?)?;
+	children.push(task_self_inode)?;
+	task_entries.insert(crate::format!("{pid}")?, DirEntry {
+		inode: task_self_inode,
+		entry_type: FileType::Link,
+	})?;
+	let task_dir_node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(task_entries));
+	let task_inode = fs.add_node(Box::new(task_dir_node)?)?;
+	children.push(task_inode)?;
+	entries.insert(String::from(b"task")?, DirEntry {
+		inode: task_inode,
+		entry_type: FileType::Directory,
+	})?;
+
+	let mut fd_inodes = HashMap::new();
+	for fdn in 0..FD_DIR_MAX {
+		let inode = fs.add_node(Box::new(FdLink {
+			pid,
+			fd: fdn,
+		})?)?;
+		children.push(inode)?;
+		fd_inodes.insert(fdn, inode)?;
+	}
+	let fd_inode = fs.add_node(Box::new(Fd {
+		pid,
+		fd_inodes,
+	})?)?;
+	children.push(fd_inode)?;
+	entries.insert(String::from(b"fd")?, DirEntry {
+		inode: fd_inode,
+		entry_type: FileType::Directory,
+	})?;
+
+	let dir_node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(entries));
+	let inode = fs.add_node(Box::new(dir_node)?)?;
+
+	Ok(ProcDir {
+		inode,
+		children,
+	})
+}
+
+/// Removes every node of the `/proc/<pid>` directory described by `dir`, including the directory
+/// itself.
+pub fn destroy(fs: &mut KernFS, dir: &ProcDir) -> Result<(), Errno> {
+	for child in &dir.children {
+		fs.remove_node(*child)?;
+	}
+	fs.remove_node(dir.inode)?;
+
+	Ok(())
+}
@@ -0,0 +1,131 @@
+//! This module implements the `stat` node, which gives access to a compact, space-separated
+//! summary of the process's status, as consumed by tools like `ps`.
+
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::NodeContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Gid;
+use crate::file::Mode;
+use crate::file::ROOT_GID;
+use crate::file::ROOT_UID;
+use crate::file::Uid;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use core::cmp::min;
+
+/// Structure representing the `stat` node.
+pub struct Stat {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for Stat {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn set_mode(&mut self, _mode: Mode) {}
+
+	fn get_uid(&self) -> Uid {
+		// A node can outlive the process it describes (e.g. a concurrent exit racing a stat()),
+		// in which case there is no real owner to report; fall back to root rather than panicking.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_UID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_euid()
+	}
+
+	fn set_uid(&mut self, _uid: Uid) {}
+
+	fn get_gid(&self) -> Gid {
+		// Same rationale as `get_uid`: a torn-down process has no real owner, so fall back to
+		// root.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_GID;
+		};
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		proc.get_egid()
+	}
+
+	fn set_gid(&mut self, _gid: Gid) {}
+
+	fn get_hard_links_count(&self) -> u16 {
+		1
+	}
+
+	fn set_hard_links_count(&mut self, _count: u16) {}
+
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn get_ctime(&self) -> u32 {
+		0
+	}
+
+	fn set_ctime(&mut self, _ctime: u32) {}
+
+	fn get_mtime(&self) -> u32 {
+		0
+	}
+
+	fn set_mtime(&mut self, _mtime: u32) {}
+
+	fn get_atime(&self) -> u32 {
+		0
+	}
+
+	fn set_atime(&mut self, _atime: u32) {}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		Ok(NodeContent::Owned(FileContent::Regular))
+	}
+
+	fn read(&mut self, offset: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buf.is_empty() {
+			return Ok((0, false));
+		}
+
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ESRCH))?;
+		let proc_guard = proc_mutex.lock();
+		let proc = proc_guard.get();
+
+		let name = proc.get_argv()
+			.iter()
+			.map(| name | unsafe { name.as_str_unchecked() })
+			.next()
+			.unwrap_or("?");
+
+		let pid = proc.get_pid();
+		let ppid = proc.get_parent_pid();
+		let pgid = proc.get_pgid();
+
+		let state = proc.get_state();
+		let state_char = state.get_char();
+
+		let priority = proc.get_priority();
+
+		// TODO Fill the remaining fields (times, memory, signals...) once the corresponding
+		// kernel-side accounting exists
+		let content = crate::format!(
+			"{pid} ({name}) {state_char} {ppid} {pgid} 0 0 -1 0 0 0 0 0 0 0 {priority} 0 1 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n"
+		)?;
+
+		let content_bytes = content.as_bytes();
+		let len = min((content_bytes.len() as u64 - offset) as usize, buf.len());
+		buf[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<(), Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -2,15 +2,18 @@
 //! process.
 
 use crate::errno::Errno;
+use crate::file::fs::kernfs::content::NodeContent;
 use crate::file::fs::kernfs::node::KernFSNode;
 use crate::file::FileContent;
 use crate::file::Gid;
 use crate::file::Mode;
+use crate::file::ROOT_GID;
+use crate::file::ROOT_UID;
 use crate::file::Uid;
 use crate::process::pid::Pid;
 use crate::process::Process;
-use crate::util::io::IO;
-use crate::util::ptr::cow::Cow;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use core::cmp::min;
 
 /// Structure representing the status node of the procfs.
@@ -24,38 +27,74 @@ impl KernFSNode for Status {
 		0o444
 	}
 
+	fn set_mode(&mut self, _mode: Mode) {}
+
 	fn get_uid(&self) -> Uid {
-		let proc_mutex = Process::get_by_pid(self.pid).unwrap();
+		// A node can outlive the process it describes (e.g. a concurrent exit racing a stat()),
+		// in which case there is no real owner to report; fall back to root rather than panicking.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_UID;
+		};
 		let proc_guard = proc_mutex.lock();
 		let proc = proc_guard.get();
 
 		proc.get_euid()
 	}
 
+	fn set_uid(&mut self, _uid: Uid) {}
+
 	fn get_gid(&self) -> Gid {
-		let proc_mutex = Process::get_by_pid(self.pid).unwrap();
+		// Same rationale as `get_uid`: a torn-down process has no real owner, so fall back to
+		// root.
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return ROOT_GID;
+		};
 		let proc_guard = proc_mutex.lock();
 		let proc = proc_guard.get();
 
 		proc.get_egid()
 	}
 
-	fn get_content<'a>(&'a self) -> Cow<'a, FileContent> {
-		Cow::from(FileContent::Regular)
+	fn set_gid(&mut self, _gid: Gid) {}
+
+	fn get_hard_links_count(&self) -> u16 {
+		1
 	}
-}
 
-impl IO for Status {
+	fn set_hard_links_count(&mut self, _count: u16) {}
+
 	fn get_size(&self) -> u64 {
 		0
 	}
 
+	fn get_ctime(&self) -> u32 {
+		0
+	}
+
+	fn set_ctime(&mut self, _ctime: u32) {}
+
+	fn get_mtime(&self) -> u32 {
+		0
+	}
+
+	fn set_mtime(&mut self, _mtime: u32) {}
+
+	fn get_atime(&self) -> u32 {
+		0
+	}
+
+	fn set_atime(&mut self, _atime: u32) {}
+
+	fn get_content(&mut self) -> Result<NodeContent<'_>, Errno> {
+		Ok(NodeContent::Owned(FileContent::Regular))
+	}
+
 	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
 		if buff.is_empty() {
 			return Ok((0, false));
 		}
 
-		let proc_mutex = Process::get_current().unwrap();
+		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ESRCH))?;
 		let proc_guard = proc_mutex.lock();
 		let proc = proc_guard.get();
 
@@ -73,6 +112,10 @@ impl IO for Status {
 
 		let pid = proc.get_pid();
 		let ppid = proc.get_parent_pid();
+		// This kernel doesn't implement `clone`-created threads yet, so a process's thread group
+		// always contains exactly itself.
+		let tgid = proc.get_tgid();
+		let threads = 1;
 
 		let uid = proc.get_uid();
 		let euid = proc.get_euid();
@@ -82,43 +125,78 @@ impl IO for Status {
 		let egid = proc.get_egid();
 		let sgid = proc.get_sgid();
 
+		// The filesystem UID/GID aren't tracked separately from the effective ones (no
+		// `setfsuid`/`setfsgid` support), so they just mirror them, as Linux itself does by
+		// default until one of those syscalls is used.
+		let fsuid = euid;
+		let fsgid = egid;
+
+		let mut groups_buf = Vec::new();
+		for (i, group) in proc.get_groups().iter().enumerate() {
+			if i > 0 {
+				groups_buf.push(b' ')?;
+			}
+			let s = crate::format!("{group}")?;
+			for b in s.as_bytes() {
+				groups_buf.push(*b)?;
+			}
+		}
+		let groups = String::from(groups_buf.as_slice())?;
+
+		let mem_space_mutex = proc.get_mem_space().ok_or_else(|| errno!(ESRCH))?;
+		let mem_space_guard = mem_space_mutex.lock();
+		let mem_space = mem_space_guard.get();
+
+		let vm_size = mem_space.get_vm_size() / 1024;
+		let vm_lck = mem_space.get_locked_size() / 1024;
+		let rss_anon = mem_space.get_rss_anon();
+		let rss_file = mem_space.get_rss_file();
+		let rss_shmem = mem_space.get_rss_shmem();
+		let vm_rss = (rss_anon + rss_file + rss_shmem) / 1024;
+		let rss_anon = rss_anon / 1024;
+		let rss_file = rss_file / 1024;
+		let rss_shmem = rss_shmem / 1024;
+		let vm_data = mem_space.get_data_size() / 1024;
+		let vm_stk = mem_space.get_stack_size() / 1024;
+		let vm_exe = mem_space.get_exec_size() / 1024;
+
 		// TODO Fill every fields with process's data
 		// Generating content
 		let content = crate::format!("Name: {name}
 Umask: {umask:4o}
 State: {state_char} ({state_name})
-Tgid: 0
+Tgid: {tgid}
 Ngid: 0
 Pid: {pid}
 PPid: {ppid}
 TracerPid: 0
-Uid: {uid} {euid} {suid} TODO
-Gid: {gid} {egid} {sgid} TODO
+Uid: {uid} {euid} {suid} {fsuid}
+Gid: {gid} {egid} {sgid} {fsgid}
 FDSize: TODO
-Groups: TODO
+Groups: {groups}
 NStgid: TODO
 NSpid: TODO
 NSpgid: TODO
 NSsid: TODO
 VmPeak: TODO kB
-VmSize: TODO kB
-VmLck: TODO kB
+VmSize: {vm_size} kB
+VmLck: {vm_lck} kB
 VmPin: TODO kB
 VmHWM: TODO kB
-VmRSS: TODO kB
-RssAnon: TODO kB
-RssFile: TODO kB
-RssShmem: TODO kB
-VmData: TODO kB
-VmStk: TODO kB
-VmExe: TODO kB
+VmRSS: {vm_rss} kB
+RssAnon: {rss_anon} kB
+RssFile: {rss_file} kB
+RssShmem: {rss_shmem} kB
+VmData: {vm_data} kB
+VmStk: {vm_stk} kB
+VmExe: {vm_exe} kB
 VmLib: TODO kB
 VmPTE: TODO kB
 VmSwap: TODO kB
 HugetlbPages: TODO kB
 CoreDumping: TODO
 THP_enabled: TODO
-Threads: TODO
+Threads: {threads}
 SigQ: TODO/TODO
 SigPnd: 0000000000000000
 ShdPnd: 0000000000000000
@@ -152,12 +230,7 @@ nonvoluntary_ctxt_switches: 0
 		Ok((len as _, eof))
 	}
 
-	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+	fn write(&mut self, _off: u64, _buf: &[u8]) -> Result<(), Errno> {
 		Err(errno!(EINVAL))
 	}
-
-	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
-		// TODO
-		todo!();
-	}
-}
\ No newline at end of file
+}
@@ -13,17 +13,33 @@ use crate::file::Uid;
 use crate::file::fs::Statfs;
 use crate::file::path::Path;
 use crate::util::IO;
+use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
 use crate::util::ptr::SharedPtr;
 use super::Filesystem;
 use super::FilesystemType;
+use super::FALLOC_FL_COLLAPSE_RANGE;
+use super::FALLOC_FL_KEEP_SIZE;
+use super::FALLOC_FL_PUNCH_HOLE;
+use super::can_write_xattr;
 use super::kernfs::KernFS;
 use super::kernfs::node::KernFSNode;
 
 /// The default maximum amount of memory the filesystem can use in bytes.
 const DEFAULT_MAX_SIZE: usize = 512 * 1024 * 1024;
 
+/// Seal forbidding any further call to `F_ADD_SEALS`.
+pub const F_SEAL_SEAL: u32 = 0b0001;
+/// Seal forbidding any operation that would grow the file's size.
+pub const F_SEAL_GROW: u32 = 0b0010;
+/// Seal forbidding any operation that would shrink the file's size.
+pub const F_SEAL_SHRINK: u32 = 0b0100;
+/// Seal forbidding any write to the file.
+pub const F_SEAL_WRITE: u32 = 0b1000;
+
 /// Returns the size in bytes used by the given node `node`.
 fn get_used_size(node: &KernFSNode) -> usize {
 	size_of::<KernFSNode>() + node.get_size() as usize
@@ -39,6 +55,15 @@ pub struct TmpFS {
 
 	/// The kernfs.
 	fs: KernFS,
+
+	/// The set of seals applied to each sealable node, keyed by inode.
+	///
+	/// A node only appears in this map if it was created with `MFD_ALLOW_SEALING`; any other
+	/// node is considered unsealable and `F_ADD_SEALS` on it fails with `EINVAL`.
+	seals: HashMap<INode, u32>,
+
+	/// Extended attributes set on each node, keyed by inode then by attribute name.
+	xattrs: HashMap<INode, HashMap<String, Vec<u8>>>,
 }
 
 impl TmpFS {
@@ -53,6 +78,9 @@ impl TmpFS {
 			size: 0,
 
 			fs: KernFS::new(String::from(b"tmpfs")?, fs_id, readonly, mountpath)?,
+
+			seals: HashMap::new(),
+			xattrs: HashMap::new(),
 		};
 
 		// Adding the root node
@@ -91,6 +119,69 @@ impl TmpFS {
 			Err(errno!(ENOSPC))
 		}
 	}
+
+	/// Creates a new anonymous, unlinked regular file backed by this tmpfs, for use with
+	/// `memfd_create`.
+	///
+	/// If `allow_sealing` is set, the file is registered as sealable and `F_ADD_SEALS`/
+	/// `F_GET_SEALS` become usable on it. Otherwise, `F_ADD_SEALS` fails with `EINVAL`.
+	pub fn create_anonymous(&mut self, uid: Uid, gid: Gid, allow_sealing: bool)
+		-> Result<INode, Errno> {
+		let node = KernFSNode::new(0o600, uid, gid, FileContent::Regular, None);
+		let size = get_used_size(&node) as _;
+
+		let mut inode = 0;
+		self.update_size(size, |fs| {
+			inode = fs.fs.add_node(Box::new(node)?)?;
+			Ok(())
+		})?;
+
+		if allow_sealing {
+			self.seals.insert(inode, 0)?;
+		}
+
+		Ok(inode)
+	}
+
+	/// Returns the current seal bitmask of the node `inode`.
+	///
+	/// If the node isn't sealable, the function returns `0`.
+	pub fn get_seals(&self, inode: INode) -> u32 {
+		self.seals.get(&inode).copied().unwrap_or(0)
+	}
+
+	/// Adds the seals in `seals` to the node `inode`.
+	///
+	/// If the node isn't sealable, the function returns `EINVAL`. If `F_SEAL_SEAL` is already set,
+	/// it returns `EPERM`.
+	pub fn add_seals(&mut self, inode: INode, seals: u32) -> Result<(), Errno> {
+		let Some(current) = self.seals.get_mut(&inode) else {
+			return Err(errno!(EINVAL));
+		};
+		if *current & F_SEAL_SEAL != 0 {
+			return Err(errno!(EPERM));
+		}
+
+		*current |= seals;
+		Ok(())
+	}
+
+	/// Checks that a write of `len` bytes starting at offset `off` on node `inode` is allowed
+	/// given its current seals, returning `EPERM` otherwise.
+	fn check_write_seals(&self, inode: INode, off: u64, len: u64, old_size: u64)
+		-> Result<(), Errno> {
+		let seals = self.get_seals(inode);
+		if seals & F_SEAL_WRITE != 0 {
+			return Err(errno!(EPERM));
+		}
+
+		let new_end = off + len;
+		if seals & F_SEAL_GROW != 0 && new_end > old_size {
+			return Err(errno!(EPERM));
+		}
+
+		Ok(())
+	}
 }
 
 impl Filesystem for TmpFS {
@@ -158,9 +249,212 @@ impl Filesystem for TmpFS {
 
 	fn write_node(&mut self, io: &mut dyn IO, inode: INode, off: u64, buf: &[u8])
 		-> Result<(), Errno> {
+		let old_size = self.fs.get_node(inode)?.get_size();
+		self.check_write_seals(inode, off, buf.len() as u64, old_size)?;
+
 		// TODO Update fs's size
 		self.fs.write_node(io, inode, off, buf)
 	}
+
+	fn get_xattr(&mut self, _io: &mut dyn IO, inode: INode, name: &String, buf: &mut [u8])
+		-> Result<usize, Errno> {
+		let value = self.xattrs.get(&inode)
+			.and_then(|attrs| attrs.get(name.as_bytes()))
+			.ok_or_else(|| errno!(ENODATA))?;
+
+		if buf.is_empty() {
+			return Ok(value.len());
+		}
+		if value.len() > buf.len() {
+			return Err(errno!(ERANGE));
+		}
+
+		buf[..value.len()].copy_from_slice(value);
+		Ok(value.len())
+	}
+
+	fn set_xattr(&mut self, _io: &mut dyn IO, inode: INode, name: &String, value: &[u8],
+		uid: Uid, _gid: Gid) -> Result<(), Errno> {
+		if !can_write_xattr(name.as_bytes(), uid) {
+			return Err(errno!(EPERM));
+		}
+
+		// Check the node exists
+		self.fs.get_node(inode)?;
+
+		let prev_len = self.xattrs.get(&inode)
+			.and_then(|attrs| attrs.get(name.as_bytes()))
+			.map(Vec::len)
+			.unwrap_or(0);
+		let cost = (name.as_bytes().len() + value.len()) as isize - prev_len as isize;
+
+		self.update_size(cost, |fs| {
+			if !fs.xattrs.contains_key(&inode) {
+				fs.xattrs.insert(inode, HashMap::new())?;
+			}
+			let attrs = fs.xattrs.get_mut(&inode).unwrap();
+			attrs.insert(name.try_clone()?, Vec::try_from(value)?)?;
+			Ok(())
+		})
+	}
+
+	fn list_xattr(&mut self, _io: &mut dyn IO, inode: INode, buf: &mut [u8])
+		-> Result<usize, Errno> {
+		let Some(attrs) = self.xattrs.get(&inode) else {
+			return Ok(0);
+		};
+
+		let required: usize = attrs.iter().map(|(name, _)| name.as_bytes().len() + 1).sum();
+		if buf.is_empty() {
+			return Ok(required);
+		}
+		if required > buf.len() {
+			return Err(errno!(ERANGE));
+		}
+
+		let mut off = 0;
+		for (name, _) in attrs.iter() {
+			let name = name.as_bytes();
+			buf[off..(off + name.len())].copy_from_slice(name);
+			buf[off + name.len()] = b'\0';
+			off += name.len() + 1;
+		}
+
+		Ok(off)
+	}
+
+	fn remove_xattr(&mut self, _io: &mut dyn IO, inode: INode, name: &String, uid: Uid,
+		_gid: Gid) -> Result<(), Errno> {
+		if !can_write_xattr(name.as_bytes(), uid) {
+			return Err(errno!(EPERM));
+		}
+
+		let Some(attrs) = self.xattrs.get_mut(&inode) else {
+			return Err(errno!(ENODATA));
+		};
+		let Some(removed) = attrs.remove(name.as_bytes()) else {
+			return Err(errno!(ENODATA));
+		};
+
+		let freed = (name.as_bytes().len() + removed.len()) as isize;
+		self.update_size(-freed, |_| Ok(()))
+	}
+
+	fn fallocate(&mut self, io: &mut dyn IO, inode: INode, mode: u32, offset: u64, len: u64)
+		-> Result<(), Errno> {
+		let punch_hole = mode & FALLOC_FL_PUNCH_HOLE != 0;
+		let keep_size = mode & FALLOC_FL_KEEP_SIZE != 0;
+		let collapse = mode & FALLOC_FL_COLLAPSE_RANGE != 0;
+		if punch_hole && !keep_size {
+			return Err(errno!(EOPNOTSUPP));
+		}
+
+		let old_size = self.fs.get_node(inode)?.get_size();
+		self.check_write_seals(inode, offset, len, old_size)?;
+
+		if collapse {
+			if self.get_seals(inode) & F_SEAL_SHRINK != 0 {
+				return Err(errno!(EPERM));
+			}
+
+			// Shift every byte past the collapsed range down by `len`, then drop the tail.
+			let mut buf = [0u8; 512];
+			let mut src = offset + len;
+			let mut dst = offset;
+			while src < old_size {
+				let n = core::cmp::min(buf.len() as u64, old_size - src) as usize;
+				self.fs.read_node(io, inode, src, &mut buf[..n])?;
+				self.fs.write_node(io, inode, dst, &buf[..n])?;
+				src += n as u64;
+				dst += n as u64;
+			}
+			let new_size = old_size - len;
+			self.update_size(-(len as isize), |fs| {
+				fs.fs.get_node_mut(inode)?.truncate(new_size)
+			})?;
+
+			return Ok(());
+		}
+
+		if punch_hole {
+			let end = core::cmp::min(offset + len, old_size);
+			if offset >= end {
+				return Ok(());
+			}
+
+			let zeroes = [0u8; 512];
+			let mut off = offset;
+			while off < end {
+				let n = core::cmp::min(zeroes.len() as u64, end - off) as usize;
+				self.fs.write_node(io, inode, off, &zeroes[..n])?;
+				off += n as u64;
+			}
+			self.update_size(-((end - offset) as isize), |_| Ok(()))?;
+
+			return Ok(());
+		}
+
+		// Default mode: reserve and zero-fill the range, growing the node if needed.
+		let new_end = offset + len;
+		if new_end > old_size {
+			let zeroes = [0u8; 512];
+			let mut off = old_size;
+			self.update_size((new_end - old_size) as isize, |fs| {
+				while off < new_end {
+					let n = core::cmp::min(zeroes.len() as u64, new_end - off) as usize;
+					fs.fs.write_node(io, inode, off, &zeroes[..n])?;
+					off += n as u64;
+				}
+				Ok(())
+			})?;
+		}
+
+		Ok(())
+	}
+}
+
+/// The mountpoint ID used for files created by `memfd_create`.
+///
+/// These files are never actually mounted anywhere; this ID only serves to route
+/// `FileLocation::Filesystem` lookups back to [`get_anonymous`].
+pub const ANONYMOUS_MOUNTPOINT_ID: u32 = u32::MAX;
+
+/// A no-op [`IO`] implementation used to call into the anonymous tmpfs.
+///
+/// The anonymous instance backing `memfd_create` files has no mountpoint and thus no backing
+/// storage device to hand to [`Filesystem`] methods. `TmpFS` (like any purely in-memory kernfs)
+/// never actually reads or writes through the `io` it is given, so this stub only exists to
+/// satisfy the trait signature.
+pub struct NullIo;
+
+impl IO for NullIo {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Ok((0, true))
+	}
+
+	fn write(&mut self, _offset: u64, _buf: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
+
+/// The tmpfs instance backing anonymous memory objects created through `memfd_create`.
+static ANONYMOUS_TMPFS: Mutex<Option<SharedPtr<TmpFS>>> = Mutex::new(None);
+
+/// Returns the shared tmpfs instance used to back `memfd_create` files, creating it on first
+/// use.
+pub fn get_anonymous() -> SharedPtr<TmpFS> {
+	let mut anon = ANONYMOUS_TMPFS.lock();
+	if anon.get().is_none() {
+		let fs = TmpFS::new(DEFAULT_MAX_SIZE, ANONYMOUS_MOUNTPOINT_ID, false, Path::root())
+			.expect("failed to create anonymous tmpfs for memfd_create");
+		*anon.get_mut() = Some(SharedPtr::new(fs).expect("out of memory"));
+	}
+
+	anon.get().as_ref().unwrap().clone()
 }
 
 /// Structure representing the tmpfs file system type.
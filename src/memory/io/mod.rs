@@ -0,0 +1,208 @@
+//! Low-level I/O primitives used by device drivers: typed port I/O, memory-mapped I/O, and
+//! physically-contiguous DMA buffers built on top of the `VMem` trait.
+
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ptr::read_volatile;
+use core::ptr::write_volatile;
+use crate::errno::Errno;
+use crate::memory;
+use crate::memory::buddy;
+
+/// Cache-disabled, write-through mapping flags suitable for DMA buffers shared with devices.
+const DMA_MAP_FLAGS: u32 = memory::vmem::x86::FLAG_CACHE_DISABLE
+	| memory::vmem::x86::FLAG_WRITE_THROUGH
+	| memory::vmem::x86::FLAG_WRITE;
+
+/// Trait implemented by the integer types usable with [`Pio`]: `u8`, `u16` and `u32`.
+pub trait PortWidth: Copy {
+	/// Reads a value from the I/O port `port`.
+	///
+	/// # Safety
+	///
+	/// The caller must ensure reading from the given port has no undesired side effect.
+	unsafe fn port_in(port: u16) -> Self;
+	/// Writes `value` to the I/O port `port`.
+	///
+	/// # Safety
+	///
+	/// The caller must ensure writing to the given port has no undesired side effect.
+	unsafe fn port_out(port: u16, value: Self);
+}
+
+impl PortWidth for u8 {
+	unsafe fn port_in(port: u16) -> Self {
+		let value: u8;
+		core::arch::asm!("in al, dx", out("al") value, in("dx") port);
+		value
+	}
+
+	unsafe fn port_out(port: u16, value: Self) {
+		core::arch::asm!("out dx, al", in("dx") port, in("al") value);
+	}
+}
+
+impl PortWidth for u16 {
+	unsafe fn port_in(port: u16) -> Self {
+		let value: u16;
+		core::arch::asm!("in ax, dx", out("ax") value, in("dx") port);
+		value
+	}
+
+	unsafe fn port_out(port: u16, value: Self) {
+		core::arch::asm!("out dx, ax", in("dx") port, in("ax") value);
+	}
+}
+
+impl PortWidth for u32 {
+	unsafe fn port_in(port: u16) -> Self {
+		let value: u32;
+		core::arch::asm!("in eax, dx", out("eax") value, in("dx") port);
+		value
+	}
+
+	unsafe fn port_out(port: u16, value: Self) {
+		core::arch::asm!("out dx, eax", in("dx") port, in("eax") value);
+	}
+}
+
+/// A type-safe handle to an x86 I/O port, reading/writing through `in`/`out` instructions.
+pub struct Pio<T: PortWidth> {
+	/// The port's address.
+	port: u16,
+
+	_phantom: PhantomData<T>,
+}
+
+impl<T: PortWidth> Pio<T> {
+	/// Creates a new instance for the port at address `port`.
+	pub const fn new(port: u16) -> Self {
+		Self {
+			port,
+
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Reads the current value of the port.
+	#[inline(always)]
+	pub fn read(&self) -> T {
+		// Safe because the port number is valid and reads/writes through this type are the
+		// caller's responsibility to use correctly, same as any other register access.
+		unsafe { T::port_in(self.port) }
+	}
+
+	/// Writes `value` to the port.
+	#[inline(always)]
+	pub fn write(&self, value: T) {
+		unsafe {
+			T::port_out(self.port, value);
+		}
+	}
+}
+
+/// A type-safe handle to a memory-mapped register, performing volatile reads/writes so accesses
+/// are never reordered or elided by the compiler.
+pub struct Mmio<T> {
+	/// The virtual address of the register.
+	addr: *mut T,
+}
+
+impl<T> Mmio<T> {
+	/// Creates a new instance for the register mapped at virtual address `addr`.
+	///
+	/// # Safety
+	///
+	/// The caller must ensure `addr` is mapped and stays valid for as long as the instance is
+	/// used.
+	pub const unsafe fn new(addr: *mut T) -> Self {
+		Self {
+			addr,
+		}
+	}
+
+	/// Performs a volatile read of the register.
+	#[inline(always)]
+	pub fn read(&self) -> T {
+		unsafe { read_volatile(self.addr) }
+	}
+
+	/// Performs a volatile write of `value` to the register.
+	#[inline(always)]
+	pub fn write(&self, value: T) {
+		unsafe {
+			write_volatile(self.addr, value);
+		}
+	}
+}
+
+// Safe to share across cores: accesses always go through the hardware, never a cached copy.
+unsafe impl<T> Sync for Mmio<T> {}
+
+/// A handle to a range of physically-contiguous pages, mapped identity-or-fixed so the physical
+/// address stays stable for the lifetime of the handle.
+///
+/// This is the building block device drivers use to allocate descriptor rings and other buffers
+/// that must be programmed into a device by physical address while remaining accessible to the
+/// CPU.
+pub struct DmaBuffer {
+	/// The physical address of the first page.
+	phys_addr: *const c_void,
+	/// The virtual address the range is mapped at.
+	virt_addr: *mut c_void,
+	/// The number of pages in the range.
+	pages: usize,
+}
+
+impl DmaBuffer {
+	/// Allocates `pages` physically-contiguous pages and maps them for DMA use.
+	pub fn alloc(pages: usize) -> Result<Self, Errno> {
+		let phys_addr = buddy::alloc_contiguous(pages, buddy::FLAG_ZONE_TYPE_KERNEL)?;
+
+		// Mapped into the kernel's own context: a separate, never-bound context's mapping would
+		// never actually be in effect for the memory the CPU touches.
+		memory::vmem::with_kernel(|vmem| {
+			vmem.map_range(phys_addr, phys_addr, pages, DMA_MAP_FLAGS)?;
+			vmem.flush();
+			Ok(())
+		}).map_err(|_| errno!(ENOMEM))?;
+
+		Ok(Self {
+			phys_addr,
+			virt_addr: phys_addr as *mut c_void,
+			pages,
+		})
+	}
+
+	/// Returns the physical base address, to be programmed into a device's descriptors.
+	pub fn phys_addr(&self) -> *const c_void {
+		self.phys_addr
+	}
+
+	/// Returns the buffer's content as a byte slice, usable by the CPU.
+	pub fn as_slice(&self) -> &[u8] {
+		unsafe {
+			core::slice::from_raw_parts(self.virt_addr as *const u8, self.pages * memory::PAGE_SIZE)
+		}
+	}
+
+	/// Returns the buffer's content as a mutable byte slice, usable by the CPU.
+	pub fn as_slice_mut(&mut self) -> &mut [u8] {
+		unsafe {
+			core::slice::from_raw_parts_mut(self.virt_addr as *mut u8, self.pages * memory::PAGE_SIZE)
+		}
+	}
+}
+
+impl Drop for DmaBuffer {
+	fn drop(&mut self) {
+		memory::vmem::with_kernel(|vmem| {
+			let _ = vmem.unmap_range(self.virt_addr, self.pages);
+			vmem.flush();
+		});
+
+		unsafe {
+			buddy::free(self.phys_addr, self.pages);
+		}
+	}
+}
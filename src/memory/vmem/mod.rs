@@ -8,6 +8,7 @@ pub mod x86;
 
 use core::ffi::c_void;
 use crate::util::boxed::Box;
+use crate::util::lock::Mutex;
 
 /// Trait representing virtual memory context handler. This trait is the interface to manipulate
 /// virtual memory on any architecture. Each architecture has its own structure implementing this
@@ -47,8 +48,28 @@ pub trait VMem {
 	/// Unmaps the given range beginning at virtual address `virtaddr` with size of `pages` pages.
 	fn unmap_range(&mut self, virtaddr: *const c_void, pages: usize) -> Result<(), ()>;
 
-	/// Clones the context, creating a new one pointing towards the same physical pages.
-	fn clone(&self) -> Result::<Self, ()> where Self: Sized;
+	/// Clones the context for a `fork()`-like operation.
+	///
+	/// Every writable user page present in `self` is remapped read-only with the COW marker
+	/// flag in both `self` and the returned context, and the physical page's reference count is
+	/// incremented so it isn't freed while either side still uses it. No page is actually
+	/// copied: the first write from either side takes a page fault, which must be routed to
+	/// [`VMem::handle_cow_fault`] to perform the real copy.
+	fn clone(&mut self) -> Result::<Self, ()> where Self: Sized;
+
+	/// Handles a page fault caused by a write to a copy-on-write page at virtual address
+	/// `virtaddr`.
+	///
+	/// If the page is shared with another context, a fresh physical frame is allocated, the
+	/// faulting page's content is copied into it, the new frame is mapped at `virtaddr` with
+	/// write permission restored, and the original frame's reference count is decremented.
+	///
+	/// If `self` is the last owner of the page (refcount reaches `1`), no copy is made: write
+	/// permission is simply restored on the page already in place.
+	///
+	/// The function returns `Err` if `virtaddr` isn't a COW page, in which case the fault is not
+	/// recoverable and must be treated as a regular segmentation violation.
+	fn handle_cow_fault(&mut self, virtaddr: *const c_void) -> Result<(), ()>;
 
 	/// Binds the virtual memory context handler.
 	fn bind(&self);
@@ -64,14 +85,30 @@ pub fn new() -> Result::<Box::<dyn VMem>, ()> {
 	Ok(Box::new(x86::X86VMem::new()?)? as Box::<dyn VMem>)
 }
 
-// TODO Handle leak
+/// The kernel's own virtual memory context, shared by mappings that must stay visible
+/// regardless of which process happens to be scheduled (the kernel image, device MMIO, DMA
+/// buffers).
+static KERNEL_VMEM: Mutex<Option<Box<dyn VMem>>> = Mutex::new(None);
+
 /// Creates and loads the kernel's memory protection, protecting its code from writing.
 pub fn kernel() {
-	if let Ok(kernel_vmem) = new() {
-		kernel_vmem.bind();
-	} else {
-		crate::kernel_panic!("Cannot initialize kernel virtual memory!", 0);
-	}
+	let kernel_vmem = match new() {
+		Ok(kernel_vmem) => kernel_vmem,
+		Err(_) => crate::kernel_panic!("Cannot initialize kernel virtual memory!", 0),
+	};
+	kernel_vmem.bind();
+
+	*KERNEL_VMEM.lock() = Some(kernel_vmem);
+}
+
+/// Calls `f` with mutable access to the kernel's virtual memory context.
+///
+/// Panics if called before [`kernel`] has run.
+pub fn with_kernel<R>(f: impl FnOnce(&mut dyn VMem) -> R) -> R {
+	let mut guard = KERNEL_VMEM.lock();
+	let kernel_vmem = guard.as_mut().expect("kernel virtual memory not initialized");
+
+	f(kernel_vmem.as_mut())
 }
 
 /// Tells whether the read-only pages protection is enabled.
@@ -0,0 +1,349 @@
+//! x86 (32-bit, non-PAE) paging-based implementation of [`VMem`].
+//!
+//! Page directories and page tables are allocated out of the kernel physical zone and, like
+//! every other kernel-owned structure in this kernel, accessed directly at their physical
+//! address since kernel memory is identity-mapped.
+
+use core::arch::asm;
+use core::ffi::c_void;
+use crate::errno::Errno;
+use crate::memory;
+use crate::memory::buddy;
+use crate::util::container::hashmap::HashMap;
+use crate::util::lock::Mutex;
+use super::VMem;
+
+/// Tells the page is present in memory.
+pub const FLAG_PRESENT: u32 = 1 << 0;
+/// Tells the page is writable.
+pub const FLAG_WRITE: u32 = 1 << 1;
+/// Tells the page is accessible from userspace.
+pub const FLAG_USER: u32 = 1 << 2;
+/// Enables write-through caching for the page.
+pub const FLAG_WRITE_THROUGH: u32 = 1 << 3;
+/// Disables caching for the page.
+pub const FLAG_CACHE_DISABLE: u32 = 1 << 4;
+/// Software-defined flag (one of the bits every x86 page table entry leaves available to the
+/// OS) marking a page as copy-on-write: present and readable, but write-protected because at
+/// least one other context shares the underlying frame.
+const FLAG_COW: u32 = 1 << 9;
+
+/// The number of entries in a page directory or a page table.
+const ENTRIES_PER_TABLE: usize = 1024;
+/// The mask of the bits of an entry that hold flags rather than a frame address.
+const FLAGS_MASK: u32 = memory::PAGE_SIZE as u32 - 1;
+
+/// The number of contexts sharing each physical frame currently mapped copy-on-write.
+///
+/// A frame is added here when it first becomes shared. [`X86VMem::handle_cow_fault`] decrements
+/// the count each time an owner takes a private copy, and removes the entry when an owner faults
+/// on a count of one, at which point that sole remaining owner writes to the frame in place.
+static COW_REFS: Mutex<HashMap<usize, usize>> = Mutex::new(HashMap::new());
+
+/// Returns the frame address held by `entry`, with the flag bits masked off.
+fn frame_of(entry: u32) -> usize {
+	(entry & !FLAGS_MASK) as usize
+}
+
+/// Returns the page directory index for `addr`.
+fn dir_index(addr: *const c_void) -> usize {
+	(addr as usize >> 22) & (ENTRIES_PER_TABLE - 1)
+}
+
+/// Returns the page table index for `addr`.
+fn table_index(addr: *const c_void) -> usize {
+	(addr as usize >> 12) & (ENTRIES_PER_TABLE - 1)
+}
+
+/// Allocates a zeroed, page-sized table (a page directory and a page table are both exactly one
+/// page long).
+fn alloc_table() -> Result<*mut u32, Errno> {
+	let frame = buddy::alloc_contiguous(1, buddy::FLAG_ZONE_TYPE_KERNEL)?;
+	let table = frame as *mut u32;
+	unsafe {
+		core::ptr::write_bytes(table, 0, ENTRIES_PER_TABLE);
+	}
+
+	Ok(table)
+}
+
+/// Invalidates the TLB entry for `addr`.
+fn invlpg(addr: *const c_void) {
+	unsafe {
+		asm!("invlpg [{}]", in(reg) addr);
+	}
+}
+
+/// Returns the current value of the `cr0` control register.
+///
+/// # Safety
+///
+/// The caller must ensure reading `cr0` has no undesired side effect.
+pub unsafe fn cr0_get() -> u32 {
+	let value: u32;
+	asm!("mov {}, cr0", out(reg) value);
+	value
+}
+
+/// Sets the bits of `cr0` set in `mask`, leaving the others untouched.
+///
+/// # Safety
+///
+/// The caller must ensure setting these bits has no undesired side effect.
+pub unsafe fn cr0_set(mask: u32) {
+	let value = cr0_get() | mask;
+	asm!("mov cr0, {}", in(reg) value);
+}
+
+/// Clears the bits of `cr0` set in `mask`, leaving the others untouched.
+///
+/// # Safety
+///
+/// The caller must ensure clearing these bits has no undesired side effect.
+pub unsafe fn cr0_clear(mask: u32) {
+	let value = cr0_get() & !mask;
+	asm!("mov cr0, {}", in(reg) value);
+}
+
+/// x86 (32-bit, non-PAE) paging context.
+pub struct X86VMem {
+	/// The physical (and, since kernel memory is identity-mapped, virtual) address of the page
+	/// directory.
+	page_dir: *mut u32,
+}
+
+impl X86VMem {
+	/// Creates a new, empty context.
+	pub fn new() -> Result<Self, Errno> {
+		Ok(Self {
+			page_dir: alloc_table()?,
+		})
+	}
+
+	/// Returns a pointer to the page table entry for `addr`.
+	///
+	/// If the underlying page table doesn't exist yet, it is allocated when `create` is set;
+	/// otherwise `None` is returned.
+	fn entry_mut(&mut self, addr: *const c_void, create: bool) -> Result<Option<*mut u32>, Errno> {
+		let dir_entry = unsafe { &mut *self.page_dir.add(dir_index(addr)) };
+		if *dir_entry & FLAG_PRESENT == 0 {
+			if !create {
+				return Ok(None);
+			}
+
+			let table = alloc_table()?;
+			*dir_entry = (table as u32) | FLAG_PRESENT | FLAG_WRITE | FLAG_USER;
+		}
+
+		let table = frame_of(*dir_entry) as *mut u32;
+		Ok(Some(unsafe { table.add(table_index(addr)) }))
+	}
+}
+
+impl VMem for X86VMem {
+	fn translate(&self, ptr: *const c_void) -> Option<*const c_void> {
+		let dir_entry = unsafe { *self.page_dir.add(dir_index(ptr)) };
+		if dir_entry & FLAG_PRESENT == 0 {
+			return None;
+		}
+
+		let table = frame_of(dir_entry) as *const u32;
+		let entry = unsafe { *table.add(table_index(ptr)) };
+		if entry & FLAG_PRESENT == 0 {
+			return None;
+		}
+
+		let offset = ptr as usize & FLAGS_MASK as usize;
+		Some((frame_of(entry) + offset) as *const c_void)
+	}
+
+	fn map(&mut self, physaddr: *const c_void, virtaddr: *const c_void, flags: u32)
+		-> Result<(), ()> {
+		let entry = self.entry_mut(virtaddr, true).map_err(|_| ())?.unwrap();
+		unsafe {
+			*entry = (physaddr as u32 & !FLAGS_MASK) | (flags & FLAGS_MASK) | FLAG_PRESENT;
+		}
+
+		Ok(())
+	}
+
+	fn map_range(&mut self, physaddr: *const c_void, virtaddr: *const c_void, pages: usize,
+		flags: u32) -> Result<(), ()> {
+		for i in 0..pages {
+			let off = i * memory::PAGE_SIZE;
+			self.map((physaddr as usize + off) as _, (virtaddr as usize + off) as _, flags)?;
+		}
+
+		Ok(())
+	}
+
+	fn unmap(&mut self, virtaddr: *const c_void) -> Result<(), ()> {
+		if let Some(entry) = self.entry_mut(virtaddr, false).map_err(|_| ())? {
+			unsafe {
+				*entry = 0;
+			}
+
+			if self.is_bound() {
+				invlpg(virtaddr);
+			}
+		}
+
+		Ok(())
+	}
+
+	fn unmap_range(&mut self, virtaddr: *const c_void, pages: usize) -> Result<(), ()> {
+		for i in 0..pages {
+			self.unmap((virtaddr as usize + i * memory::PAGE_SIZE) as _)?;
+		}
+
+		Ok(())
+	}
+
+	fn clone(&mut self) -> Result<Self, ()> {
+		let mut new = Self::new().map_err(|_| ())?;
+
+		for d in 0..ENTRIES_PER_TABLE {
+			let dir_entry = unsafe { *self.page_dir.add(d) };
+			if dir_entry & FLAG_PRESENT == 0 {
+				continue;
+			}
+
+			// Kernel-only page tables are identical in every context: share the same table
+			// instead of walking and copying its entries one by one. Only userspace-writable
+			// pages ever need copy-on-write.
+			if dir_entry & FLAG_USER == 0 {
+				let new_dir_entry = unsafe { &mut *new.page_dir.add(d) };
+				*new_dir_entry = dir_entry;
+				continue;
+			}
+
+			let table = frame_of(dir_entry) as *mut u32;
+			let new_table = alloc_table().map_err(|_| ())?;
+			let new_dir_entry = unsafe { &mut *new.page_dir.add(d) };
+			*new_dir_entry = (new_table as u32) | (dir_entry & FLAGS_MASK);
+
+			for t in 0..ENTRIES_PER_TABLE {
+				let entry = unsafe { &mut *table.add(t) };
+				if *entry & FLAG_PRESENT == 0 {
+					continue;
+				}
+
+				// A writable user page is put under copy-on-write in both contexts instead of
+				// actually duplicated: the first write from either side takes a fault, routed to
+				// `handle_cow_fault`.
+				if *entry & FLAG_WRITE != 0 {
+					*entry = (*entry & !FLAG_WRITE) | FLAG_COW;
+
+					// `self` keeps running in this very address space right after `clone()` returns
+					// (the `fork()` case): without a flush, its CPU can keep a stale writable TLB
+					// entry for the page just downgraded and write straight through it, bypassing
+					// COW entirely.
+					if self.is_bound() {
+						let virtaddr = (d << 22) | (t << 12);
+						invlpg(virtaddr as *const c_void);
+					}
+				}
+
+				// A page that was already COW (shared from an earlier `clone()` of `self`) gains
+				// one more owner here too, not just a page transitioning from writable to COW for
+				// the first time: `new` is a third (or later) context now mapping the same frame,
+				// and the refcount has to reflect every context still sharing it, or
+				// `handle_cow_fault` will let one of them write the frame in place while another
+				// still maps it.
+				if *entry & FLAG_COW != 0 {
+					let frame = frame_of(*entry);
+					let mut refs = COW_REFS.lock();
+					let count = refs.get(&frame).copied().unwrap_or(1);
+					refs.insert(frame, count + 1).map_err(|_| ())?;
+				}
+
+				unsafe {
+					*new_table.add(t) = *entry;
+				}
+			}
+		}
+
+		Ok(new)
+	}
+
+	fn handle_cow_fault(&mut self, virtaddr: *const c_void) -> Result<(), ()> {
+		let entry = self.entry_mut(virtaddr, false).map_err(|_| ())?.ok_or(())?;
+		let value = unsafe { *entry };
+		if value & FLAG_COW == 0 {
+			return Err(());
+		}
+
+		let frame = frame_of(value);
+		let mut refs = COW_REFS.lock();
+		let count = refs.get(&frame).copied().unwrap_or(1);
+
+		if count <= 1 {
+			refs.remove(&frame);
+			drop(refs);
+
+			unsafe {
+				*entry = (value & !FLAG_COW) | FLAG_WRITE;
+			}
+		} else {
+			refs.insert(frame, count - 1).map_err(|_| ())?;
+			drop(refs);
+
+			let new_frame = buddy::alloc_contiguous(1, buddy::FLAG_ZONE_TYPE_KERNEL)
+				.map_err(|_| ())?;
+			unsafe {
+				core::ptr::copy_nonoverlapping(frame as *const u8, new_frame as *mut u8,
+					memory::PAGE_SIZE);
+				*entry = (new_frame as u32 & !FLAGS_MASK) | (value & FLAGS_MASK & !FLAG_COW)
+					| FLAG_WRITE;
+			}
+		}
+
+		if self.is_bound() {
+			invlpg(virtaddr);
+		}
+
+		Ok(())
+	}
+
+	fn bind(&self) {
+		unsafe {
+			asm!("mov cr3, {}", in(reg) self.page_dir as u32);
+		}
+	}
+
+	fn is_bound(&self) -> bool {
+		let cr3: u32;
+		unsafe {
+			asm!("mov {}, cr3", out(reg) cr3);
+		}
+
+		cr3 == self.page_dir as u32
+	}
+
+	fn flush(&self) {
+		// Reloading cr3 flushes every non-global TLB entry; there is no finer-grained "flush
+		// modifications since last flush" operation to do instead.
+		if self.is_bound() {
+			self.bind();
+		}
+	}
+}
+
+impl Drop for X86VMem {
+	fn drop(&mut self) {
+		for d in 0..ENTRIES_PER_TABLE {
+			let dir_entry = unsafe { *self.page_dir.add(d) };
+			// Kernel-only tables are shared with other contexts (see `clone`), not owned by this
+			// one; only user tables are freed here.
+			if dir_entry & FLAG_PRESENT != 0 && dir_entry & FLAG_USER != 0 {
+				unsafe {
+					buddy::free(frame_of(dir_entry) as _, 1);
+				}
+			}
+		}
+
+		unsafe {
+			buddy::free(self.page_dir as _, 1);
+		}
+	}
+}
@@ -0,0 +1,539 @@
+//! DHCPv4 client, auto-configuring an interface's [`BindAddress`] and default route instead of
+//! requiring them to be set up by hand.
+//!
+//! There is no socket layer wired up in this kernel yet ([`crate::net::SocketDomain::AfInet`]
+//! `SockDgram` sockets aren't backed by anything), so this client builds and parses its own
+//! Ethernet/IPv4/UDP framing directly on top of [`Interface::read`]/[`Interface::write`] rather
+//! than going through one. Whatever eventually drives the socket layer can become a thin wrapper
+//! around the same BOOTP/DHCP framing helpers below.
+//!
+//! There is likewise no wall-clock source available to this module, so lease timing
+//! ([`DhcpClient::tick`]) is driven by an elapsed-seconds counter the caller supplies, rather than
+//! by reading the time itself.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::net::buff::BuffList;
+use crate::net::get_iface;
+use crate::net::register_default_route;
+use crate::net::unregister_default_route;
+use crate::net::Address;
+use crate::net::BindAddress;
+use crate::net::Interface;
+use crate::net::MAC;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+
+/// The client's well-known UDP port.
+const CLIENT_PORT: u16 = 68;
+/// The server's well-known UDP port.
+const SERVER_PORT: u16 = 67;
+
+const OP_BOOTREQUEST: u8 = 1;
+const OP_BOOTREPLY: u8 = 2;
+const HTYPE_ETHERNET: u8 = 1;
+
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+
+const OPT_SUBNET_MASK: u8 = 1;
+const OPT_ROUTER: u8 = 3;
+const OPT_DNS: u8 = 6;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_END: u8 = 255;
+const OPT_PAD: u8 = 0;
+
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+/// Size of the fixed BOOTP header, up to and including the 128-byte `file` field.
+const BOOTP_HEADER_LEN: usize = 236;
+
+/// Initial retransmit timeout, in seconds, doubled on every unanswered retry.
+const INITIAL_TIMEOUT: u32 = 4;
+/// Gives up after this many unanswered retries and restarts from `DHCPDISCOVER`.
+const MAX_RETRIES: u32 = 5;
+
+/// The lease obtained from a DHCP server.
+#[derive(Clone)]
+pub struct Lease {
+	/// The assigned IPv4 address.
+	pub addr: [u8; 4],
+	/// The prefix length derived from the subnet mask option.
+	pub subnet_mask: u8,
+	/// The default gateway, if the server provided one.
+	pub gateway: Option<[u8; 4]>,
+	/// The DNS servers advertised by the server, for resolvers to use.
+	pub dns: Vec<[u8; 4]>,
+	/// The server that granted the lease.
+	server_id: [u8; 4],
+
+	/// Total lease duration, in seconds.
+	lease_time: u32,
+	/// Seconds elapsed since the lease was granted.
+	elapsed: u32,
+}
+
+impl Lease {
+	/// Renewal time: the client should try to renew with the original server past this point.
+	fn t1(&self) -> u32 {
+		self.lease_time / 2
+	}
+
+	/// Rebinding time: the client should broadcast a renewal request to any server past this
+	/// point.
+	fn t2(&self) -> u32 {
+		self.lease_time * 7 / 8
+	}
+}
+
+/// The client's state machine, following the standard DISCOVER -> OFFER -> REQUEST -> ACK
+/// handshake.
+enum State {
+	/// No lease; about to send, or waiting on, a `DHCPDISCOVER`.
+	Selecting {
+		/// Seconds until the next retransmit.
+		timeout: u32,
+		/// Seconds elapsed since the last `DHCPDISCOVER` was sent.
+		waited: u32,
+		/// Number of `DHCPDISCOVER`s sent so far without an answer.
+		retries: u32,
+	},
+	/// An offer was received; about to send, or waiting on, a `DHCPREQUEST`.
+	Requesting {
+		offer: Lease,
+		timeout: u32,
+		waited: u32,
+		retries: u32,
+	},
+	/// A lease is active.
+	Bound(Lease),
+}
+
+/// A DHCPv4 client bound to a single interface.
+pub struct DhcpClient {
+	iface_name: String,
+	/// The transaction id of the outstanding request, used to reject replies that don't match it.
+	xid: u32,
+	state: State,
+}
+
+/// Computes the Internet checksum (RFC 1071) of `data`, treated as a sequence of big-endian
+/// 16-bit words (the last byte is padded with zero if `data` has an odd length).
+fn checksum(data: &[u8]) -> u16 {
+	let mut sum: u32 = 0;
+	let mut iter = data.chunks_exact(2);
+	for chunk in &mut iter {
+		sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+	}
+	if let [last] = iter.remainder() {
+		sum += (*last as u32) << 8;
+	}
+	while sum >> 16 != 0 {
+		sum = (sum & 0xffff) + (sum >> 16);
+	}
+	!(sum as u16)
+}
+
+/// Appends a BOOTP/DHCP option (code, length, value) to `buf`.
+fn push_option(buf: &mut Vec<u8>, code: u8, value: &[u8]) -> Result<(), Errno> {
+	buf.push(code)?;
+	buf.push(value.len() as u8)?;
+	buf.extend_from_slice(value)?;
+	Ok(())
+}
+
+/// Builds a full Ethernet/IPv4/UDP/DHCP frame carrying a BOOTP message of type `msg_type`.
+///
+/// `requested_ip`/`server_id` are included as the corresponding DHCP options when
+/// non-`None`; `src_mac` becomes both the Ethernet source address and the `chaddr` field.
+fn build_frame(
+	xid: u32,
+	msg_type: u8,
+	src_mac: &MAC,
+	requested_ip: Option<[u8; 4]>,
+	server_id: Option<[u8; 4]>,
+) -> Result<Vec<u8>, Errno> {
+	let mut dhcp = Vec::new();
+	dhcp.push(OP_BOOTREQUEST)?;
+	dhcp.push(HTYPE_ETHERNET)?;
+	dhcp.push(6)?; // hlen
+	dhcp.push(0)?; // hops
+	dhcp.extend_from_slice(&xid.to_be_bytes())?;
+	dhcp.extend_from_slice(&0u16.to_be_bytes())?; // secs
+	dhcp.extend_from_slice(&0x8000u16.to_be_bytes())?; // flags: ask for a broadcast reply
+	dhcp.extend_from_slice(&[0; 4])?; // ciaddr
+	dhcp.extend_from_slice(&[0; 4])?; // yiaddr
+	dhcp.extend_from_slice(&[0; 4])?; // siaddr
+	dhcp.extend_from_slice(&[0; 4])?; // giaddr
+	dhcp.extend_from_slice(src_mac)?;
+	dhcp.extend_from_slice(&[0; 10])?; // chaddr padding, up to 16 bytes
+	dhcp.extend_from_slice(&[0; 64])?; // sname
+	dhcp.extend_from_slice(&[0; 128])?; // file
+	dhcp.extend_from_slice(&MAGIC_COOKIE)?;
+
+	push_option(&mut dhcp, OPT_MESSAGE_TYPE, &[msg_type])?;
+	if let Some(ip) = requested_ip {
+		push_option(&mut dhcp, OPT_REQUESTED_IP, &ip)?;
+	}
+	if let Some(id) = server_id {
+		push_option(&mut dhcp, OPT_SERVER_ID, &id)?;
+	}
+	push_option(
+		&mut dhcp,
+		55, // parameter request list
+		&[OPT_SUBNET_MASK, OPT_ROUTER, OPT_DNS, OPT_LEASE_TIME],
+	)?;
+	dhcp.push(OPT_END)?;
+
+	let udp_len = 8 + dhcp.len();
+	let mut udp = Vec::new();
+	udp.extend_from_slice(&CLIENT_PORT.to_be_bytes())?;
+	udp.extend_from_slice(&SERVER_PORT.to_be_bytes())?;
+	udp.extend_from_slice(&(udp_len as u16).to_be_bytes())?;
+	udp.extend_from_slice(&0u16.to_be_bytes())?; // checksum: 0 means "not computed", valid over IPv4
+	udp.extend_from_slice(&dhcp)?;
+
+	let ip_len = 20 + udp.len();
+	let mut ip = Vec::new();
+	ip.push(0x45)?; // version 4, IHL 5
+	ip.push(0)?; // DSCP/ECN
+	ip.extend_from_slice(&(ip_len as u16).to_be_bytes())?;
+	ip.extend_from_slice(&0u16.to_be_bytes())?; // identification
+	ip.extend_from_slice(&0u16.to_be_bytes())?; // flags/fragment offset
+	ip.push(64)?; // TTL
+	ip.push(17)?; // protocol: UDP
+	ip.extend_from_slice(&0u16.to_be_bytes())?; // checksum, filled in below
+	ip.extend_from_slice(&[0, 0, 0, 0])?; // source: unconfigured
+	ip.extend_from_slice(&[255, 255, 255, 255])?; // destination: broadcast
+	let ip_checksum = checksum(&ip);
+	ip[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+	ip.extend_from_slice(&udp)?;
+
+	let mut frame = Vec::new();
+	frame.extend_from_slice(&[0xff; 6])?; // destination: broadcast
+	frame.extend_from_slice(src_mac)?;
+	frame.extend_from_slice(&0x0800u16.to_be_bytes())?; // ethertype: IPv4
+	frame.extend_from_slice(&ip)?;
+
+	Ok(frame)
+}
+
+/// A DHCP message parsed out of a received frame.
+struct Message {
+	xid: u32,
+	msg_type: u8,
+	yiaddr: [u8; 4],
+	subnet_mask: Option<u8>,
+	router: Option<[u8; 4]>,
+	dns: Vec<[u8; 4]>,
+	server_id: Option<[u8; 4]>,
+	lease_time: u32,
+}
+
+/// Parses `frame` as an Ethernet/IPv4/UDP frame carrying a BOOTP/DHCP reply, returning `None` if
+/// it isn't one.
+fn parse_frame(frame: &[u8]) -> Option<Message> {
+	const ETH_LEN: usize = 14;
+	if frame.len() < ETH_LEN + 20 + 8 + BOOTP_HEADER_LEN + 4 {
+		return None;
+	}
+	if frame[12..14] != [0x08, 0x00] {
+		return None;
+	}
+
+	let ip = &frame[ETH_LEN..];
+	let ihl = (ip[0] & 0xf) as usize * 4;
+	if ip[9] != 17 || ip.len() < ihl + 8 {
+		return None;
+	}
+
+	let udp = &ip[ihl..];
+	let src_port = u16::from_be_bytes([udp[0], udp[1]]);
+	let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+	if src_port != SERVER_PORT || dst_port != CLIENT_PORT {
+		return None;
+	}
+
+	let dhcp = &udp[8..];
+	if dhcp.len() < BOOTP_HEADER_LEN + 4 || dhcp[0] != OP_BOOTREPLY {
+		return None;
+	}
+	if dhcp[BOOTP_HEADER_LEN..(BOOTP_HEADER_LEN + 4)] != MAGIC_COOKIE {
+		return None;
+	}
+
+	let xid = u32::from_be_bytes(dhcp[4..8].try_into().unwrap());
+	let yiaddr = dhcp[16..20].try_into().unwrap();
+
+	let mut msg_type = None;
+	let mut subnet_mask = None;
+	let mut router = None;
+	let mut dns = Vec::new();
+	let mut server_id = None;
+	let mut lease_time = 0;
+
+	let mut off = BOOTP_HEADER_LEN + 4;
+	while off < dhcp.len() {
+		let code = dhcp[off];
+		if code == OPT_END {
+			break;
+		}
+		if code == OPT_PAD {
+			off += 1;
+			continue;
+		}
+		if off + 1 >= dhcp.len() {
+			break;
+		}
+		let len = dhcp[off + 1] as usize;
+		let start = off + 2;
+		if start + len > dhcp.len() {
+			break;
+		}
+		let value = &dhcp[start..(start + len)];
+
+		match code {
+			OPT_MESSAGE_TYPE if len == 1 => msg_type = Some(value[0]),
+			OPT_SUBNET_MASK if len == 4 => {
+				subnet_mask = Some(u32::from_be_bytes(value.try_into().unwrap()).count_ones() as u8)
+			}
+			OPT_ROUTER if len >= 4 => router = Some(value[0..4].try_into().unwrap()),
+			OPT_DNS => {
+				for chunk in value.chunks_exact(4) {
+					if dns.push(chunk.try_into().unwrap()).is_err() {
+						break;
+					}
+				}
+			}
+			OPT_SERVER_ID if len == 4 => server_id = Some(value.try_into().unwrap()),
+			OPT_LEASE_TIME if len == 4 => {
+				lease_time = u32::from_be_bytes(value.try_into().unwrap())
+			}
+			_ => {}
+		}
+
+		off = start + len;
+	}
+
+	Some(Message {
+		xid,
+		msg_type: msg_type?,
+		yiaddr,
+		subnet_mask,
+		router,
+		dns,
+		server_id,
+		lease_time,
+	})
+}
+
+impl DhcpClient {
+	/// Starts DHCP configuration of the interface named `iface_name`, sending the first
+	/// `DHCPDISCOVER` immediately.
+	pub fn start(iface_name: String, xid: u32) -> Result<Self, Errno> {
+		let mut client = Self {
+			iface_name,
+			xid,
+			state: State::Selecting {
+				timeout: INITIAL_TIMEOUT,
+				waited: 0,
+				retries: 0,
+			},
+		};
+		client.send_discover()?;
+		Ok(client)
+	}
+
+	fn iface(&self) -> Result<Arc<Mutex<dyn Interface>>, Errno> {
+		get_iface(self.iface_name.as_bytes()).ok_or_else(|| errno!(ENODEV))
+	}
+
+	fn send(&self, msg_type: u8, requested_ip: Option<[u8; 4]>, server_id: Option<[u8; 4]>)
+		-> Result<(), Errno> {
+		let iface = self.iface()?;
+		let mut iface = iface.lock();
+		let mac = *iface.get().get_mac();
+		let frame = build_frame(self.xid, msg_type, &mac, requested_ip, server_id)?;
+		iface.get_mut().write(&BuffList::from(frame.as_slice()))
+	}
+
+	fn send_discover(&self) -> Result<(), Errno> {
+		self.send(DHCPDISCOVER, None, None)
+	}
+
+	fn send_request(&self, offer: &Lease) -> Result<(), Errno> {
+		self.send(DHCPREQUEST, Some(offer.addr), Some(offer.server_id))
+	}
+
+	/// Installs `lease`'s address and default route on the interface.
+	fn apply_lease(&self, lease: &Lease) -> Result<(), Errno> {
+		let iface = self.iface()?;
+		let mut iface = iface.lock();
+		iface.get_mut().get_addresses_mut().push(BindAddress {
+			addr: Address::IPv4(lease.addr),
+			subnet_mask: lease.subnet_mask,
+		})?;
+		drop(iface);
+
+		if let Some(gateway) = lease.gateway {
+			register_default_route(self.iface_name.try_clone()?, Address::IPv4(gateway))?;
+		}
+
+		Ok(())
+	}
+
+	/// Removes `lease`'s address and default route from the interface, e.g. because the lease
+	/// expired.
+	fn revoke_lease(&self, lease: &Lease) -> Result<(), Errno> {
+		let iface = self.iface()?;
+		let mut iface = iface.lock();
+		let addresses = iface.get_mut().get_addresses_mut();
+		if let Some(pos) = addresses.iter().position(|a| a.addr == Address::IPv4(lease.addr)) {
+			addresses.remove(pos);
+		}
+		drop(iface);
+
+		if lease.gateway.is_some() {
+			unregister_default_route(self.iface_name.as_bytes());
+		}
+
+		Ok(())
+	}
+
+	/// Feeds a received frame to the client. Frames for another transaction, or that aren't
+	/// BOOTP/DHCP replies, are silently ignored.
+	pub fn on_frame(&mut self, frame: &[u8]) -> Result<(), Errno> {
+		let Some(msg) = parse_frame(frame) else {
+			return Ok(());
+		};
+		if msg.xid != self.xid {
+			return Ok(());
+		}
+
+		match (&self.state, msg.msg_type) {
+			(State::Selecting { .. }, DHCPOFFER) => {
+				let Some(server_id) = msg.server_id else {
+					return Ok(());
+				};
+				let offer = Lease {
+					addr: msg.yiaddr,
+					subnet_mask: msg.subnet_mask.unwrap_or(24),
+					gateway: msg.router,
+					dns: msg.dns,
+					server_id,
+					lease_time: msg.lease_time,
+					elapsed: 0,
+				};
+				self.send_request(&offer)?;
+				self.state = State::Requesting {
+					offer,
+					timeout: INITIAL_TIMEOUT,
+					waited: 0,
+					retries: 0,
+				};
+			}
+
+			(State::Requesting { offer, .. }, DHCPACK) => {
+				let mut lease = offer.clone();
+				lease.lease_time = msg.lease_time.max(1);
+				self.apply_lease(&lease)?;
+				self.state = State::Bound(lease);
+			}
+			(State::Requesting { .. }, DHCPNAK) => {
+				self.restart()?;
+			}
+
+			_ => {}
+		}
+
+		Ok(())
+	}
+
+	/// Advances the client's timers by `elapsed_secs` seconds: retransmitting while waiting for a
+	/// reply, and renewing or expiring an active lease.
+	pub fn tick(&mut self, elapsed_secs: u32) -> Result<(), Errno> {
+		match &mut self.state {
+			State::Selecting {
+				timeout,
+				waited,
+				retries,
+			} => {
+				*waited += elapsed_secs;
+				if *waited < *timeout {
+					return Ok(());
+				}
+				*waited = 0;
+				*retries += 1;
+				if *retries > MAX_RETRIES {
+					*retries = 0;
+					*timeout = INITIAL_TIMEOUT;
+				} else {
+					*timeout *= 2;
+				}
+				self.send_discover()?;
+			}
+
+			State::Requesting {
+				offer,
+				timeout,
+				waited,
+				retries,
+			} => {
+				*waited += elapsed_secs;
+				if *waited < *timeout {
+					return Ok(());
+				}
+				*waited = 0;
+				*retries += 1;
+				if *retries > MAX_RETRIES {
+					let xid = self.xid;
+					self.state = State::Selecting {
+						timeout: INITIAL_TIMEOUT,
+						waited: 0,
+						retries: 0,
+					};
+					self.xid = xid.wrapping_add(1);
+					self.send_discover()?;
+				} else {
+					*timeout *= 2;
+					let offer = offer.clone();
+					self.send_request(&offer)?;
+				}
+			}
+
+			State::Bound(lease) => {
+				lease.elapsed += elapsed_secs;
+				if lease.elapsed >= lease.lease_time {
+					let lease = lease.clone();
+					self.revoke_lease(&lease)?;
+					self.restart()?;
+				} else if lease.elapsed == lease.t1() || lease.elapsed == lease.t2() {
+					self.send_request(&lease.clone())?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Drops any in-progress or active lease and restarts from `DHCPDISCOVER` with a fresh
+	/// transaction id.
+	fn restart(&mut self) -> Result<(), Errno> {
+		self.xid = self.xid.wrapping_add(1);
+		self.state = State::Selecting {
+			timeout: INITIAL_TIMEOUT,
+			waited: 0,
+			retries: 0,
+		};
+		self.send_discover()
+	}
+}
@@ -1,17 +1,20 @@
 //! This module implements the network stack.
 
 pub mod buff;
+pub mod dhcp;
 pub mod lo;
 pub mod netlink;
 pub mod osi;
 pub mod proto;
 pub mod sockaddr;
+pub mod virtio_net;
 
 use crate::errno::Errno;
 use crate::file::Gid;
 use crate::file::Uid;
 use crate::file::ROOT_GID;
 use crate::file::ROOT_UID;
+use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
@@ -76,12 +79,17 @@ pub trait Interface {
 
 	/// Tells whether the interface is UP.
 	fn is_up(&self) -> bool;
+	/// Sets whether the interface is UP.
+	fn set_up(&mut self, up: bool);
 
 	/// Returns the mac address of the interface.
 	fn get_mac(&self) -> &MAC;
 
 	/// Returns the list of addresses bound to the interface.
 	fn get_addresses(&self) -> &[BindAddress];
+	/// Returns a mutable reference to the list of addresses bound to the interface, so rtnetlink
+	/// handlers can add or remove entries.
+	fn get_addresses_mut(&mut self) -> &mut Vec<BindAddress>;
 
 	/// Reads data from the network interface and writes it into `buff`.
 	fn read(&mut self, buff: &mut [u8]) -> Result<(), Errno>;
@@ -105,22 +113,6 @@ pub struct Route {
 }
 
 impl Route {
-	/// Tells whether the route matches the given address.
-	pub fn is_matching(&self, addr: &Address) -> bool {
-		// Check gateway
-		if &self.gateway == addr {
-			return true;
-		}
-
-		let Some(ref dst) = self.dst else {
-			// Default route
-			return true;
-		};
-
-		// Check with netmask
-		dst.is_matching(addr)
-	}
-
 	/// Compares the current route with the given route `other`.
 	///
 	/// Ordering is done so that the best route is the greatest.
@@ -157,11 +149,249 @@ impl Route {
 	}
 }
 
+/// Returns the big-endian byte representation of `addr`.
+fn address_bytes(addr: &Address) -> &[u8] {
+	match addr {
+		Address::IPv4(bytes) => &bytes[..],
+		Address::IPv6(bytes) => &bytes[..],
+	}
+}
+
+/// A node of a binary radix (Patricia) trie, keyed bit by bit on a destination address.
+///
+/// A node represents the prefix spelled out by the bits consumed on the path from the root to
+/// it; `routes` holds every installed route whose destination prefix terminates exactly there.
+struct TrieNode {
+	/// Routes terminating at this node.
+	routes: Vec<Route>,
+	/// Child reached when the next prefix bit is `0`.
+	zero: Option<Box<TrieNode>>,
+	/// Child reached when the next prefix bit is `1`.
+	one: Option<Box<TrieNode>>,
+}
+
+impl TrieNode {
+	const fn new() -> Self {
+		Self {
+			routes: Vec::new(),
+			zero: None,
+			one: None,
+		}
+	}
+
+	/// Removes and returns the first route at this node for which `pred` returns `true`.
+	fn remove_match<F: Fn(&Route) -> bool>(&mut self, pred: F) -> Option<Route> {
+		let pos = self.routes.iter().position(pred)?;
+		Some(self.routes.remove(pos))
+	}
+
+	/// Calls `f` on every route stored at or below this node, depth-first.
+	fn for_each<F: FnMut(&Route) -> Result<(), Errno>>(&self, f: &mut F) -> Result<(), Errno> {
+		for route in &self.routes {
+			f(route)?;
+		}
+		if let Some(zero) = &self.zero {
+			zero.for_each(f)?;
+		}
+		if let Some(one) = &self.one {
+			one.for_each(f)?;
+		}
+		Ok(())
+	}
+}
+
+/// Returns the value of the `i`-th bit of `bytes`, numbered from the most significant bit of
+/// `bytes[0]`.
+fn bit_at(bytes: &[u8], i: usize) -> bool {
+	bytes[i / 8] & (0x80 >> (i % 8)) != 0
+}
+
+/// A longest-prefix-match routing table for a single address family, as a binary radix (Patricia)
+/// trie keyed on the bits of each route's destination prefix.
+///
+/// Lookups walk the trie consuming bits of the query address, remembering the deepest node
+/// visited that carries at least one route; that node holds the longest matching prefix, and the
+/// default route (no destination, i.e. a `0`-bit prefix) lives at the root so it is always the
+/// fallback. This turns a lookup that used to scan every entry into one proportional to the
+/// address width.
+struct RouteTrie {
+	root: TrieNode,
+}
+
+impl RouteTrie {
+	const fn new() -> Self {
+		Self {
+			root: TrieNode::new(),
+		}
+	}
+
+	/// Returns the node for the first `bits` bits of `prefix`, creating intermediate nodes as
+	/// needed.
+	///
+	/// `bits` comes from attacker-controlled input (an rtnetlink `RTM_NEWROUTE`'s `dst_len`), so
+	/// it is validated against `prefix`'s actual length here rather than trusted to fit.
+	fn node_mut(&mut self, prefix: &[u8], bits: u8) -> Result<&mut TrieNode, Errno> {
+		if bits as usize > prefix.len() * 8 {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut node = &mut self.root;
+		for i in 0..bits as usize {
+			let slot = if bit_at(prefix, i) {
+				&mut node.one
+			} else {
+				&mut node.zero
+			};
+			if slot.is_none() {
+				*slot = Some(Box::new(TrieNode::new())?);
+			}
+			node = slot.as_mut().unwrap();
+		}
+		Ok(node)
+	}
+
+	/// Inserts `route` under the prefix given by its destination, or at the root if it is a
+	/// default route.
+	fn insert(&mut self, route: Route) -> Result<(), Errno> {
+		let (prefix, bits) = match &route.dst {
+			Some(dst) => (address_bytes(&dst.addr), dst.subnet_mask),
+			None => (&[][..], 0),
+		};
+		self.node_mut(prefix, bits)?.routes.push(route)
+	}
+
+	/// Removes and returns the first route under `prefix`/`bits` for which `pred` returns `true`.
+	///
+	/// Returns `None`, same as an ordinary miss, if `bits` doesn't fit within `prefix` (see
+	/// [`Self::node_mut`]) rather than indexing out of bounds.
+	fn remove<F: Fn(&Route) -> bool>(&mut self, prefix: &[u8], bits: u8, pred: F) -> Option<Route> {
+		if bits as usize > prefix.len() * 8 {
+			return None;
+		}
+
+		let mut node = &mut self.root;
+		for i in 0..bits as usize {
+			let child = if bit_at(prefix, i) {
+				&mut node.one
+			} else {
+				&mut node.zero
+			};
+			node = child.as_deref_mut()?;
+		}
+		node.remove_match(pred)
+	}
+
+	/// Walks the trie along the bits of `addr`, returning the best route (per [`Route::cmp_for`])
+	/// among those at the deepest visited node that carries any, i.e. the longest matching
+	/// prefix. The default route at the root is the fallback when no longer prefix matches.
+	fn lookup(&self, addr: &Address) -> Option<&Route> {
+		let addr_bytes = address_bytes(addr);
+
+		let mut node = &self.root;
+		let mut best = node.routes.iter().max_by(|a, b| a.cmp_for(b, addr));
+		for i in 0..(addr_bytes.len() * 8) {
+			let child = if bit_at(addr_bytes, i) {
+				&node.one
+			} else {
+				&node.zero
+			};
+			let Some(child) = child.as_deref() else {
+				break;
+			};
+			node = child;
+
+			if let Some(candidate) = node.routes.iter().max_by(|a, b| a.cmp_for(b, addr)) {
+				best = Some(candidate);
+			}
+		}
+
+		best
+	}
+
+	/// Calls `f` on every route stored in the trie, depth-first.
+	fn for_each<F: FnMut(&Route) -> Result<(), Errno>>(&self, f: &mut F) -> Result<(), Errno> {
+		self.root.for_each(f)
+	}
+}
+
+/// The routing table, split into one [`RouteTrie`] per address family so IPv4 and IPv6
+/// destinations are never compared against each other.
+pub struct RoutingTable {
+	ipv4: RouteTrie,
+	ipv6: RouteTrie,
+}
+
+impl RoutingTable {
+	const fn new() -> Self {
+		Self {
+			ipv4: RouteTrie::new(),
+			ipv6: RouteTrie::new(),
+		}
+	}
+
+	/// Returns the trie for `addr`'s address family.
+	fn trie_for(&self, addr: &Address) -> &RouteTrie {
+		match addr {
+			Address::IPv4(_) => &self.ipv4,
+			Address::IPv6(_) => &self.ipv6,
+		}
+	}
+
+	/// Returns the mutable trie for `addr`'s address family.
+	fn trie_for_mut(&mut self, addr: &Address) -> &mut RouteTrie {
+		match addr {
+			Address::IPv4(_) => &mut self.ipv4,
+			Address::IPv6(_) => &mut self.ipv6,
+		}
+	}
+
+	/// Inserts `route`, keyed by its gateway's address family.
+	pub fn insert(&mut self, route: Route) -> Result<(), Errno> {
+		self.trie_for_mut(&route.gateway).insert(route)
+	}
+
+	/// Removes and returns the first route of address family `family` under `dst` (or the root,
+	/// if `dst` is `None`) for which `pred` returns `true`.
+	pub fn remove<F: Fn(&Route) -> bool>(
+		&mut self,
+		family: &Address,
+		dst: Option<&BindAddress>,
+		pred: F,
+	) -> Option<Route> {
+		let (prefix, bits) = match dst {
+			Some(dst) => (address_bytes(&dst.addr), dst.subnet_mask),
+			None => (&[][..], 0),
+		};
+		self.trie_for_mut(family).remove(prefix, bits, pred)
+	}
+
+	/// Removes and returns the default route (if any) matching `pred`, trying both address
+	/// families since the caller may not know which one it was registered under.
+	pub fn remove_default<F: Fn(&Route) -> bool>(&mut self, pred: F) -> Option<Route> {
+		self.ipv4
+			.root
+			.remove_match(&pred)
+			.or_else(|| self.ipv6.root.remove_match(&pred))
+	}
+
+	/// Returns the best route (per [`Route::cmp_for`]) matching `addr`, i.e. the route at the
+	/// longest matching destination prefix.
+	pub fn lookup(&self, addr: &Address) -> Option<&Route> {
+		self.trie_for(addr).lookup(addr)
+	}
+
+	/// Calls `f` on every route in the table, IPv4 routes first.
+	pub fn for_each<F: FnMut(&Route) -> Result<(), Errno>>(&self, mut f: F) -> Result<(), Errno> {
+		self.ipv4.for_each(&mut f)?;
+		self.ipv6.for_each(&mut f)
+	}
+}
+
 /// The list of network interfaces.
 pub static INTERFACES: Mutex<HashMap<String, Arc<Mutex<dyn Interface>>>> =
 	Mutex::new(HashMap::new());
 /// The routing table.
-pub static ROUTING_TABLE: Mutex<Vec<Route>> = Mutex::new(Vec::new());
+pub static ROUTING_TABLE: Mutex<RoutingTable> = Mutex::new(RoutingTable::new());
 
 /// Registers the given network interface.
 ///
@@ -193,14 +423,33 @@ pub fn get_iface(name: &[u8]) -> Option<Arc<Mutex<dyn Interface>>> {
 /// Returns the network interface to be used to transmit a packet to the given destination address.
 pub fn get_iface_for(addr: &Address) -> Option<Arc<Mutex<dyn Interface>>> {
 	let routing_table = ROUTING_TABLE.lock();
-	let route = routing_table
-		.iter()
-		.filter(|route| route.is_matching(addr))
-		.max_by(|a, b| a.cmp_for(&b, addr))?;
-
+	let route = routing_table.lookup(addr)?;
 	get_iface(&route.iface)
 }
 
+/// Registers the default route (no destination prefix) through `gateway`, reached via `iface`,
+/// replacing any previous default route set for that interface.
+///
+/// Intended for protocols that configure routing automatically, such as the DHCP client.
+pub fn register_default_route(iface: String, gateway: Address) -> Result<(), Errno> {
+	let mut routing_table = ROUTING_TABLE.lock();
+	routing_table.remove_default(|route| {
+		route.dst.is_none() && route.iface.as_bytes() == iface.as_bytes()
+	});
+	routing_table.insert(Route {
+		dst: None,
+		iface,
+		gateway,
+		metric: 0,
+	})
+}
+
+/// Removes the default route set for `iface`, if any.
+pub fn unregister_default_route(iface: &[u8]) {
+	let mut routing_table = ROUTING_TABLE.lock();
+	routing_table.remove_default(|route| route.dst.is_none() && route.iface.as_bytes() == iface);
+}
+
 /// Enumeration of socket domains.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum SocketDomain {
@@ -0,0 +1,463 @@
+//! rtnetlink (`NETLINK_ROUTE`) lets userspace tools such as `ip`/`iproute2` read and mutate the
+//! kernel's network state: interfaces, their addresses, and the routing table.
+//!
+//! This module parses the standard netlink framing (`nlmsghdr` + type-specific body + a sequence
+//! of `rtattr` TLVs) and dispatches `RTM_*` requests against [`super::INTERFACES`] and
+//! [`super::ROUTING_TABLE`]. It is driven by the `AF_NETLINK`/`NETLINK_ROUTE` socket code, which
+//! hands it a raw request buffer and collects whatever reply messages it appends.
+//!
+//! Interfaces don't carry a persistent numeric index anywhere else in the kernel, so this module
+//! derives one from the iteration order of [`super::INTERFACES`]; that order is stable for as
+//! long as no interface is registered or unregistered in between, which is good enough for the
+//! `ip link`/`ip addr`/`ip route` use cases this subsystem targets.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::net::get_iface;
+use crate::net::Address;
+use crate::net::BindAddress;
+use crate::net::Route;
+use crate::net::INTERFACES;
+use crate::net::ROUTING_TABLE;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::TryClone;
+
+/// Request: dump all matching entries instead of just one.
+pub const NLM_F_DUMP: u16 = 0x300;
+
+/// Marks the end of a dump.
+const NLMSG_DONE: u16 = 3;
+/// Carries an errno payload in response to a request (`0` on success).
+const NLMSG_ERROR: u16 = 2;
+
+const RTM_NEWLINK: u16 = 16;
+const RTM_GETLINK: u16 = 18;
+const RTM_NEWADDR: u16 = 20;
+const RTM_DELADDR: u16 = 21;
+const RTM_GETADDR: u16 = 22;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_DELROUTE: u16 = 25;
+const RTM_GETROUTE: u16 = 26;
+
+const IFLA_ADDRESS: u16 = 1;
+const IFLA_IFNAME: u16 = 3;
+
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_GATEWAY: u16 = 5;
+const RTA_PRIORITY: u16 = 6;
+
+/// `IFF_UP`: the interface is administratively up.
+const IFF_UP: u32 = 0x1;
+
+/// `AF_INET`, as used in `rtattr`/`ifaddrmsg` address families.
+const AF_INET: u8 = 2;
+/// `AF_INET6`, as used in `rtattr`/`ifaddrmsg` address families.
+const AF_INET6: u8 = 10;
+
+/// The netlink message header, common to every request and reply.
+#[repr(C)]
+struct NlMsgHdr {
+	len: u32,
+	msg_type: u16,
+	flags: u16,
+	seq: u32,
+	pid: u32,
+}
+
+/// `ifinfomsg`, the body of `RTM_*LINK` messages.
+#[repr(C)]
+struct IfInfoMsg {
+	family: u8,
+	pad: u8,
+	iface_type: u16,
+	index: i32,
+	flags: u32,
+	change: u32,
+}
+
+/// `ifaddrmsg`, the body of `RTM_*ADDR` messages.
+#[repr(C)]
+struct IfAddrMsg {
+	family: u8,
+	prefixlen: u8,
+	flags: u8,
+	scope: u8,
+	index: u32,
+}
+
+/// `rtmsg`, the body of `RTM_*ROUTE` messages.
+#[repr(C)]
+struct RtMsg {
+	family: u8,
+	dst_len: u8,
+	src_len: u8,
+	tos: u8,
+	table: u8,
+	protocol: u8,
+	scope: u8,
+	rtm_type: u8,
+	flags: u32,
+}
+
+/// Aligns `n` up to the next 4-byte boundary, as `rtattr`/`nlmsghdr` framing requires.
+const fn align4(n: usize) -> usize {
+	(n + 3) & !3
+}
+
+/// A parsed `rtattr` TLV.
+struct Attr<'a> {
+	attr_type: u16,
+	value: &'a [u8],
+}
+
+/// Parses the sequence of `rtattr` TLVs in `buf`, calling `f` with each one.
+fn for_each_attr<F: FnMut(Attr<'_>)>(buf: &[u8], mut f: F) {
+	let mut off = 0;
+	while off + 4 <= buf.len() {
+		let len = u16::from_ne_bytes([buf[off], buf[off + 1]]) as usize;
+		let attr_type = u16::from_ne_bytes([buf[off + 2], buf[off + 3]]);
+		if len < 4 || off + len > buf.len() {
+			break;
+		}
+
+		f(Attr {
+			attr_type,
+			value: &buf[(off + 4)..(off + len)],
+		});
+
+		off += align4(len);
+	}
+}
+
+/// Parses a raw address `bytes` according to the rtnetlink address `family` (`AF_INET`/
+/// `AF_INET6`).
+fn parse_addr(family: u8, bytes: &[u8]) -> Option<Address> {
+	match (family, bytes.len()) {
+		(AF_INET, 4) => Some(Address::IPv4(bytes.try_into().unwrap())),
+		(AF_INET6, 16) => Some(Address::IPv6(bytes.try_into().unwrap())),
+		_ => None,
+	}
+}
+
+/// Returns the wire family and raw bytes for `addr`.
+fn addr_bytes(addr: &Address) -> (u8, &[u8]) {
+	match addr {
+		Address::IPv4(a) => (AF_INET, a),
+		Address::IPv6(a) => (AF_INET6, a),
+	}
+}
+
+/// Returns the names of every registered interface, in the stable order this module indexes them
+/// by.
+fn iface_names() -> Result<Vec<String>, Errno> {
+	let interfaces = INTERFACES.lock();
+	let mut names = Vec::new();
+	for (name, _) in interfaces.iter() {
+		names.push(name.try_clone()?)?;
+	}
+	Ok(names)
+}
+
+/// Returns the name of the interface at position `index` in [`INTERFACES`]'s iteration order.
+fn iface_name_at(index: u32) -> Option<String> {
+	let interfaces = INTERFACES.lock();
+	interfaces
+		.iter()
+		.nth(index as usize)
+		.and_then(|(name, _)| name.try_clone().ok())
+}
+
+/// Appends a bare `nlmsghdr` (used for `NLMSG_DONE`/`NLMSG_ERROR`, and as a prefix for every
+/// reply with `len` fixed up once its body has been written).
+fn push_header(
+	reply: &mut Vec<u8>,
+	len: u32,
+	msg_type: u16,
+	flags: u16,
+	seq: u32,
+	pid: u32,
+) -> Result<(), Errno> {
+	reply.extend_from_slice(&len.to_ne_bytes())?;
+	reply.extend_from_slice(&msg_type.to_ne_bytes())?;
+	reply.extend_from_slice(&flags.to_ne_bytes())?;
+	reply.extend_from_slice(&seq.to_ne_bytes())?;
+	reply.extend_from_slice(&pid.to_ne_bytes())?;
+	Ok(())
+}
+
+/// Fixes up the `len` field of the message whose header starts at `start`, to cover what has
+/// been appended to `reply` since.
+fn fixup_len(reply: &mut [u8], start: usize) {
+	let len = (reply.len() - start) as u32;
+	reply[start..(start + 4)].copy_from_slice(&len.to_ne_bytes());
+}
+
+/// Appends one `rtattr` TLV to `reply`.
+fn push_attr(reply: &mut Vec<u8>, attr_type: u16, value: &[u8]) -> Result<(), Errno> {
+	let len = (4 + value.len()) as u16;
+	reply.extend_from_slice(&len.to_ne_bytes())?;
+	reply.extend_from_slice(&attr_type.to_ne_bytes())?;
+	reply.extend_from_slice(value)?;
+	for _ in 0..(align4(value.len()) - value.len()) {
+		reply.push(0)?;
+	}
+	Ok(())
+}
+
+/// Appends a `RTM_NEWLINK` reply describing interface `index`/`name`.
+fn push_link(reply: &mut Vec<u8>, hdr: &NlMsgHdr, index: u32, name: &[u8]) -> Result<(), Errno> {
+	let Some(iface) = get_iface(name) else {
+		return Ok(());
+	};
+	let iface = iface.lock();
+	let iface = iface.get();
+
+	let start = reply.len();
+	push_header(reply, 0, RTM_NEWLINK, 0, hdr.seq, hdr.pid)?;
+	let info = IfInfoMsg {
+		family: 0,
+		pad: 0,
+		iface_type: 0,
+		index: index as i32,
+		flags: if iface.is_up() { IFF_UP } else { 0 },
+		change: 0,
+	};
+	reply.extend_from_slice(unsafe {
+		core::slice::from_raw_parts(
+			&info as *const _ as *const u8,
+			core::mem::size_of::<IfInfoMsg>(),
+		)
+	})?;
+	push_attr(reply, IFLA_IFNAME, iface.get_name())?;
+	push_attr(reply, IFLA_ADDRESS, iface.get_mac())?;
+	fixup_len(reply, start);
+
+	Ok(())
+}
+
+/// Appends one `RTM_NEWADDR` reply per address bound to interface `index`/`name`.
+fn push_addrs(reply: &mut Vec<u8>, hdr: &NlMsgHdr, index: u32, name: &[u8]) -> Result<(), Errno> {
+	let Some(iface) = get_iface(name) else {
+		return Ok(());
+	};
+	let iface = iface.lock();
+
+	for addr in iface.get().get_addresses() {
+		let start = reply.len();
+		push_header(reply, 0, RTM_NEWADDR, 0, hdr.seq, hdr.pid)?;
+		let (family, raw) = addr_bytes(&addr.addr);
+		let info = IfAddrMsg {
+			family,
+			prefixlen: addr.subnet_mask,
+			flags: 0,
+			scope: 0,
+			index,
+		};
+		reply.extend_from_slice(unsafe {
+			core::slice::from_raw_parts(
+				&info as *const _ as *const u8,
+				core::mem::size_of::<IfAddrMsg>(),
+			)
+		})?;
+		push_attr(reply, IFA_ADDRESS, raw)?;
+		push_attr(reply, IFA_LOCAL, raw)?;
+		fixup_len(reply, start);
+	}
+
+	Ok(())
+}
+
+/// Handles `RTM_NEWADDR`/`RTM_DELADDR`: adds or removes an address on the interface designated by
+/// `ifaddrmsg::index`.
+fn handle_addr_update(hdr: &NlMsgHdr, body: &[u8]) -> Result<(), Errno> {
+	if body.len() < core::mem::size_of::<IfAddrMsg>() {
+		return Err(errno!(EINVAL));
+	}
+	let info = unsafe { &*(body.as_ptr() as *const IfAddrMsg) };
+
+	let mut raw_addr = None;
+	for_each_attr(&body[core::mem::size_of::<IfAddrMsg>()..], |attr| {
+		if attr.attr_type == IFA_ADDRESS || attr.attr_type == IFA_LOCAL {
+			raw_addr = Some(attr.value);
+		}
+	});
+	let raw_addr = raw_addr.ok_or_else(|| errno!(EINVAL))?;
+	let addr = parse_addr(info.family, raw_addr).ok_or_else(|| errno!(EINVAL))?;
+
+	let name = iface_name_at(info.index).ok_or_else(|| errno!(ENODEV))?;
+	let iface = get_iface(name.as_bytes()).ok_or_else(|| errno!(ENODEV))?;
+	let mut iface = iface.lock();
+	let addresses = iface.get_mut().get_addresses_mut();
+
+	if hdr.msg_type == RTM_NEWADDR {
+		addresses.push(BindAddress {
+			addr,
+			subnet_mask: info.prefixlen,
+		})?;
+	} else if let Some(pos) = addresses.iter().position(|a| a.addr == addr) {
+		addresses.remove(pos);
+	}
+
+	Ok(())
+}
+
+/// Handles `RTM_NEWROUTE`/`RTM_DELROUTE`.
+fn handle_route_update(hdr: &NlMsgHdr, body: &[u8]) -> Result<(), Errno> {
+	if body.len() < core::mem::size_of::<RtMsg>() {
+		return Err(errno!(EINVAL));
+	}
+	let info = unsafe { &*(body.as_ptr() as *const RtMsg) };
+
+	let mut dst = None;
+	let mut gateway = None;
+	let mut oif = None;
+	let mut metric = 0u32;
+	for_each_attr(&body[core::mem::size_of::<RtMsg>()..], |attr| match attr.attr_type {
+		RTA_DST => dst = parse_addr(info.family, attr.value),
+		RTA_GATEWAY => gateway = parse_addr(info.family, attr.value),
+		RTA_OIF if attr.value.len() == 4 => {
+			oif = Some(u32::from_ne_bytes(attr.value.try_into().unwrap()))
+		}
+		RTA_PRIORITY if attr.value.len() == 4 => {
+			metric = u32::from_ne_bytes(attr.value.try_into().unwrap())
+		}
+		_ => {}
+	});
+
+	let gateway = gateway.ok_or_else(|| errno!(EINVAL))?;
+	let iface = oif.and_then(iface_name_at).ok_or_else(|| errno!(ENODEV))?;
+	let dst = dst.map(|addr| BindAddress {
+		addr,
+		subnet_mask: info.dst_len,
+	});
+
+	// `Route`'s fields are private to `net`; this module is one of its descendants, so it can set
+	// them directly instead of routing through a constructor built only for this one caller.
+	let mut routing_table = ROUTING_TABLE.lock();
+	if hdr.msg_type == RTM_NEWROUTE {
+		routing_table.insert(Route {
+			dst,
+			iface,
+			gateway,
+			metric,
+		})?;
+	} else {
+		routing_table.remove(&gateway, dst.as_ref(), |route| route.gateway == gateway);
+	}
+
+	Ok(())
+}
+
+/// Processes a single rtnetlink request contained in `request`, appending every reply message
+/// (including `NLMSG_DONE`/`NLMSG_ERROR` framing) to `reply`.
+pub fn process(request: &[u8], reply: &mut Vec<u8>) -> Result<(), Errno> {
+	if request.len() < core::mem::size_of::<NlMsgHdr>() {
+		return Err(errno!(EINVAL));
+	}
+	let hdr = unsafe { &*(request.as_ptr() as *const NlMsgHdr) };
+	if (hdr.len as usize) < core::mem::size_of::<NlMsgHdr>() {
+		return Err(errno!(EINVAL));
+	}
+	let body_end = core::cmp::min(hdr.len as usize, request.len());
+	let body = &request[core::mem::size_of::<NlMsgHdr>()..body_end];
+	let dump = hdr.flags & NLM_F_DUMP != 0;
+
+	let result = match hdr.msg_type {
+		RTM_GETLINK if dump => {
+			for (index, name) in iface_names()?.iter().enumerate() {
+				push_link(reply, hdr, index as u32, name.as_bytes())?;
+			}
+			Ok(())
+		}
+		RTM_GETLINK => (|| {
+			let mut name = None;
+			if body.len() >= core::mem::size_of::<IfInfoMsg>() {
+				for_each_attr(&body[core::mem::size_of::<IfInfoMsg>()..], |attr| {
+					if attr.attr_type == IFLA_IFNAME {
+						name = Some(attr.value);
+					}
+				});
+			}
+			let name = name.ok_or_else(|| errno!(EINVAL))?;
+
+			let names = iface_names()?;
+			let index = names.iter().position(|n| n.as_bytes() == name);
+			if let Some(index) = index {
+				push_link(reply, hdr, index as u32, name)?;
+			}
+			Ok(())
+		})(),
+
+		RTM_GETADDR => {
+			for (index, name) in iface_names()?.iter().enumerate() {
+				push_addrs(reply, hdr, index as u32, name.as_bytes())?;
+			}
+			Ok(())
+		}
+		RTM_NEWADDR | RTM_DELADDR => handle_addr_update(hdr, body),
+
+		RTM_GETROUTE => {
+			let routing_table = ROUTING_TABLE.lock();
+			routing_table.for_each(|route| {
+				let start = reply.len();
+				push_header(reply, 0, RTM_NEWROUTE, 0, hdr.seq, hdr.pid)?;
+				let (family, dst_len) = route
+					.dst
+					.as_ref()
+					.map(|dst| (addr_bytes(&dst.addr).0, dst.subnet_mask))
+					.unwrap_or((AF_INET, 0));
+				let info = RtMsg {
+					family,
+					dst_len,
+					src_len: 0,
+					tos: 0,
+					table: 254,
+					protocol: 0,
+					scope: 0,
+					rtm_type: 1,
+					flags: 0,
+				};
+				reply.extend_from_slice(unsafe {
+					core::slice::from_raw_parts(
+						&info as *const _ as *const u8,
+						core::mem::size_of::<RtMsg>(),
+					)
+				})?;
+				if let Some(dst) = &route.dst {
+					push_attr(reply, RTA_DST, addr_bytes(&dst.addr).1)?;
+				}
+				push_attr(reply, RTA_GATEWAY, addr_bytes(&route.gateway).1)?;
+				push_attr(reply, RTA_PRIORITY, &route.metric.to_ne_bytes())?;
+				fixup_len(reply, start);
+				Ok(())
+			})
+		}
+		RTM_NEWROUTE | RTM_DELROUTE => handle_route_update(hdr, body),
+
+		_ => Err(errno!(EOPNOTSUPP)),
+	};
+
+	if result.is_ok() && dump {
+		push_header(
+			reply,
+			core::mem::size_of::<NlMsgHdr>() as u32,
+			NLMSG_DONE,
+			0,
+			hdr.seq,
+			hdr.pid,
+		)?;
+	} else {
+		let errno = result.as_ref().err().map(Errno::as_errno).unwrap_or(0);
+		let start = reply.len();
+		push_header(reply, 0, NLMSG_ERROR, 0, hdr.seq, hdr.pid)?;
+		reply.extend_from_slice(&(-errno).to_ne_bytes())?;
+		fixup_len(reply, start);
+	}
+
+	Ok(())
+}
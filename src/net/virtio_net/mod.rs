@@ -0,0 +1,436 @@
+//! virtio-net driver, implementing the legacy (pre-1.0, port-I/O) virtio transport.
+//!
+//! A virtio-net device exposes two virtqueues: receive queue 0, which the driver keeps stocked
+//! with empty, writable descriptors for the device to fill in as packets arrive, and transmit
+//! queue 1, into which the driver places descriptors pointing at packets to send. Both queues
+//! share the same ring layout, implemented by [`Virtqueue`].
+//!
+//! There is no PCI bus enumeration in this kernel yet, so [`VirtioNet::probe`] takes the device's
+//! legacy I/O-space base port directly (as would be read from a PCI BAR) rather than discovering
+//! it itself; whatever probes the PCI bus in the future is expected to call it with that port.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::memory;
+use crate::memory::io::DmaBuffer;
+use crate::memory::io::Pio;
+use crate::memory::io::PortWidth;
+use crate::net::buff::BuffList;
+use crate::net::register_iface;
+use crate::net::unregister_iface;
+use crate::net::Interface;
+use crate::net::BindAddress;
+use crate::net::MAC;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+
+/// Legacy virtio-net I/O port layout, as offsets from the device's base port.
+mod reg {
+	pub const DEVICE_FEATURES: u16 = 0x00;
+	pub const DRIVER_FEATURES: u16 = 0x04;
+	pub const QUEUE_ADDRESS: u16 = 0x08;
+	pub const QUEUE_SIZE: u16 = 0x0c;
+	pub const QUEUE_SELECT: u16 = 0x0e;
+	pub const QUEUE_NOTIFY: u16 = 0x10;
+	pub const DEVICE_STATUS: u16 = 0x12;
+	pub const ISR_STATUS: u16 = 0x13;
+	/// The device-specific configuration space starts here (the `virtio_net_config::mac` field).
+	pub const DEVICE_CONFIG: u16 = 0x14;
+}
+
+const STATUS_ACKNOWLEDGE: u8 = 1;
+const STATUS_DRIVER: u8 = 2;
+const STATUS_DRIVER_OK: u8 = 4;
+const STATUS_FEATURES_OK: u8 = 8;
+
+/// `VIRTIO_NET_F_MAC`: the device provides a fixed MAC address in its configuration space.
+const VIRTIO_NET_F_MAC: u32 = 1 << 5;
+/// `VIRTIO_NET_F_STATUS`: the device reports its link status, instead of assuming it is always up.
+const VIRTIO_NET_F_STATUS: u32 = 1 << 16;
+
+/// `VIRTIO_NET_S_LINK_UP`: set in the device's status register when the link is up.
+const VIRTIO_NET_S_LINK_UP: u16 = 1;
+
+/// Index of the receive virtqueue.
+const QUEUE_RX: u16 = 0;
+/// Index of the transmit virtqueue.
+const QUEUE_TX: u16 = 1;
+
+/// The number of descriptors in each virtqueue. Must be a power of two.
+const QUEUE_SIZE: u16 = 256;
+
+/// The `virtio_net_hdr` prepended to every packet on both the RX and TX rings.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct NetHdr {
+	flags: u8,
+	gso_type: u8,
+	hdr_len: u16,
+	gso_size: u16,
+	csum_start: u16,
+	csum_offset: u16,
+}
+
+/// The largest Ethernet frame this driver accepts, plus room for [`NetHdr`].
+const BUFFER_SIZE: usize = core::mem::size_of::<NetHdr>() + 1514;
+
+/// A single entry in the descriptor table.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct VirtqDesc {
+	addr: u64,
+	len: u32,
+	flags: u16,
+	next: u16,
+}
+
+/// `VIRTQ_DESC_F_WRITE`: the device writes to this buffer (used for RX descriptors).
+///
+/// Every packet fits in a single physically-contiguous [`DmaBuffer`], so this driver never needs
+/// `VIRTQ_DESC_F_NEXT` to chain descriptors together.
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+/// The driver-owned ring of descriptor indices the device should process.
+#[repr(C)]
+struct VirtqAvail {
+	flags: u16,
+	idx: u16,
+	ring: [u16; QUEUE_SIZE as usize],
+}
+
+/// One entry in the device-owned "used" ring: a descriptor chain the device has finished with.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct VirtqUsedElem {
+	id: u32,
+	len: u32,
+}
+
+/// The device-owned ring of descriptor chains the device has finished processing.
+#[repr(C)]
+struct VirtqUsed {
+	flags: u16,
+	idx: u16,
+	ring: [VirtqUsedElem; QUEUE_SIZE as usize],
+}
+
+/// A legacy-layout virtqueue: a descriptor table plus the avail/used rings, backed by a single
+/// contiguous DMA allocation as the legacy transport requires.
+struct Virtqueue {
+	mem: DmaBuffer,
+	/// Index of the next free descriptor to hand out.
+	free_head: u16,
+	/// Number of descriptors handed to the device but not yet reclaimed from the used ring.
+	num_used: u16,
+	/// The last `used.idx` this driver has processed.
+	last_used_idx: u16,
+}
+
+impl Virtqueue {
+	const DESC_TABLE_SIZE: usize = core::mem::size_of::<VirtqDesc>() * QUEUE_SIZE as usize;
+	const AVAIL_SIZE: usize = core::mem::size_of::<VirtqAvail>();
+	/// The legacy layout starts the used ring on the first page boundary past the avail ring.
+	const USED_OFFSET: usize =
+		(Self::DESC_TABLE_SIZE + Self::AVAIL_SIZE).div_ceil(memory::PAGE_SIZE) * memory::PAGE_SIZE;
+
+	fn new() -> Result<Self, Errno> {
+		let total = Self::USED_OFFSET + core::mem::size_of::<VirtqUsed>();
+		let pages = total.div_ceil(memory::PAGE_SIZE).max(1);
+		let mut mem = DmaBuffer::alloc(pages)?;
+
+		mem.as_slice_mut().fill(0);
+		let mut queue = Self {
+			mem,
+			free_head: 0,
+			num_used: 0,
+			last_used_idx: 0,
+		};
+		// Chain every descriptor into the free list through its `next` field.
+		for i in 0..(QUEUE_SIZE - 1) {
+			queue.desc_mut(i).next = i + 1;
+		}
+
+		Ok(queue)
+	}
+
+	fn desc_mut(&mut self, index: u16) -> &mut VirtqDesc {
+		let descs = self.mem.as_slice_mut().as_mut_ptr() as *mut VirtqDesc;
+		unsafe { &mut *descs.add(index as usize) }
+	}
+
+	fn avail_mut(&mut self) -> &mut VirtqAvail {
+		let ptr = unsafe { self.mem.as_slice_mut().as_mut_ptr().add(Self::DESC_TABLE_SIZE) };
+		unsafe { &mut *(ptr as *mut VirtqAvail) }
+	}
+
+	fn used(&self) -> &VirtqUsed {
+		let ptr = unsafe {
+			self.mem.as_slice().as_ptr().add(Self::USED_OFFSET)
+		};
+		unsafe { &*(ptr as *const VirtqUsed) }
+	}
+
+	/// Publishes descriptor `head` to the device by appending it to the avail ring.
+	fn publish(&mut self, head: u16) {
+		let avail = self.avail_mut();
+		let slot = avail.idx % QUEUE_SIZE;
+		avail.ring[slot as usize] = head;
+		// Ensures the descriptor and avail-ring writes are visible before the index bump that
+		// tells the device they're ready.
+		core::sync::atomic::fence(core::sync::atomic::Ordering::Release);
+		avail.idx = avail.idx.wrapping_add(1);
+	}
+
+	/// Reclaims one finished descriptor chain from the used ring, if any, returning its head
+	/// index and the number of bytes the device wrote into it.
+	fn reclaim(&mut self) -> Option<(u16, u32)> {
+		let used = self.used();
+		if self.last_used_idx == used.idx {
+			return None;
+		}
+		let elem = used.ring[(self.last_used_idx % QUEUE_SIZE) as usize];
+		self.last_used_idx = self.last_used_idx.wrapping_add(1);
+		self.num_used -= 1;
+		Some((elem.id as u16, elem.len))
+	}
+}
+
+/// A virtio-net network interface.
+pub struct VirtioNet {
+	name: String,
+	io_base: u16,
+	mac: MAC,
+	link_status_negotiated: bool,
+
+	rx: Virtqueue,
+	tx: Virtqueue,
+	/// Backing storage for every RX descriptor's buffer, indexed by descriptor index.
+	rx_buffers: Vec<DmaBuffer>,
+	/// Backing storage for in-flight TX descriptors, indexed by descriptor index. `None` while
+	/// the descriptor isn't in flight.
+	tx_buffers: Vec<Option<DmaBuffer>>,
+
+	addresses: Vec<BindAddress>,
+}
+
+impl VirtioNet {
+	fn reg<T: PortWidth>(&self, offset: u16) -> Pio<T> {
+		Pio::new(self.io_base + offset)
+	}
+
+	/// Selects virtqueue `index` and returns the queue size the device reports for it.
+	fn select_queue(&self, index: u16) -> u16 {
+		self.reg::<u16>(reg::QUEUE_SELECT).write(index);
+		self.reg::<u16>(reg::QUEUE_SIZE).read()
+	}
+
+	/// Tells the device the physical address of virtqueue `index`'s memory (as a page frame
+	/// number, per the legacy transport).
+	fn set_queue_address(&self, index: u16, mem: &DmaBuffer) {
+		self.reg::<u16>(reg::QUEUE_SELECT).write(index);
+		let pfn = (mem.phys_addr() as usize / memory::PAGE_SIZE) as u32;
+		self.reg::<u32>(reg::QUEUE_ADDRESS).write(pfn);
+	}
+
+	/// Probes a virtio-net device whose legacy I/O-space registers start at `io_base`, negotiates
+	/// features, sets up the RX/TX virtqueues, and registers the resulting interface under `name`.
+	pub fn probe(name: String, io_base: u16) -> Result<(), Errno> {
+		let status_reg = Pio::<u8>::new(io_base + reg::DEVICE_STATUS);
+		status_reg.write(0);
+		status_reg.write(STATUS_ACKNOWLEDGE);
+		status_reg.write(STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+
+		let device_features = Pio::<u32>::new(io_base + reg::DEVICE_FEATURES).read();
+		let driver_features = device_features & (VIRTIO_NET_F_MAC | VIRTIO_NET_F_STATUS);
+		Pio::<u32>::new(io_base + reg::DRIVER_FEATURES).write(driver_features);
+		status_reg.write(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK);
+
+		let mut mac = [0u8; 6];
+		if driver_features & VIRTIO_NET_F_MAC != 0 {
+			for (i, byte) in mac.iter_mut().enumerate() {
+				*byte = Pio::<u8>::new(io_base + reg::DEVICE_CONFIG + i as u16).read();
+			}
+		}
+
+		let mut tx_buffers = Vec::new();
+		for _ in 0..QUEUE_SIZE {
+			tx_buffers.push(None)?;
+		}
+
+		let mut dev = Self {
+			name,
+			io_base,
+			mac,
+			link_status_negotiated: driver_features & VIRTIO_NET_F_STATUS != 0,
+			rx: Virtqueue::new()?,
+			tx: Virtqueue::new()?,
+			rx_buffers: Vec::new(),
+			tx_buffers,
+			addresses: Vec::new(),
+		};
+
+		dev.setup_queue(QUEUE_RX)?;
+		dev.setup_queue(QUEUE_TX)?;
+		dev.fill_rx_ring()?;
+
+		status_reg.write(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK | STATUS_DRIVER_OK);
+
+		let name = dev.name.try_clone()?;
+		register_iface(name, dev)
+	}
+
+	/// Unregisters the interface named `name`. The function doesn't check that the interface is a
+	/// virtio-net device.
+	pub fn remove(name: &[u8]) {
+		unregister_iface(name);
+	}
+
+	fn setup_queue(&self, index: u16) -> Result<(), Errno> {
+		let size = self.select_queue(index);
+		if size < QUEUE_SIZE {
+			return Err(errno!(ENODEV));
+		}
+		let mem = match index {
+			QUEUE_RX => &self.rx.mem,
+			_ => &self.tx.mem,
+		};
+		self.set_queue_address(index, mem);
+		Ok(())
+	}
+
+	/// Hands every RX descriptor an empty, writable buffer so the device can start filling them
+	/// in as packets arrive.
+	fn fill_rx_ring(&mut self) -> Result<(), Errno> {
+		for i in 0..QUEUE_SIZE {
+			let buf = DmaBuffer::alloc(BUFFER_SIZE.div_ceil(memory::PAGE_SIZE).max(1))?;
+			let phys = buf.phys_addr() as u64;
+			self.rx_buffers.push(buf)?;
+
+			let desc = self.rx.desc_mut(i);
+			desc.addr = phys;
+			desc.len = BUFFER_SIZE as u32;
+			desc.flags = VIRTQ_DESC_F_WRITE;
+			desc.next = 0;
+
+			self.rx.publish(i);
+			self.rx.num_used += 1;
+		}
+		self.reg::<u16>(reg::QUEUE_NOTIFY).write(QUEUE_RX);
+
+		Ok(())
+	}
+}
+
+impl Interface for VirtioNet {
+	fn get_name(&self) -> &[u8] {
+		self.name.as_bytes()
+	}
+
+	fn is_up(&self) -> bool {
+		if !self.link_status_negotiated {
+			return true;
+		}
+		let status = Pio::<u16>::new(self.io_base + reg::DEVICE_CONFIG + 6).read();
+		status & VIRTIO_NET_S_LINK_UP != 0
+	}
+
+	fn set_up(&mut self, _up: bool) {
+		// The link state of a virtio-net device is driven by the host side; there is nothing to
+		// set from the guest.
+	}
+
+	fn get_mac(&self) -> &MAC {
+		&self.mac
+	}
+
+	fn get_addresses(&self) -> &[BindAddress] {
+		&self.addresses
+	}
+
+	fn get_addresses_mut(&mut self) -> &mut Vec<BindAddress> {
+		&mut self.addresses
+	}
+
+	/// Drains one finished RX descriptor, if any, into `buff`. Returns `Ok(())` having written
+	/// nothing if no packet is available; a poll loop is expected to call this repeatedly.
+	fn read(&mut self, buff: &mut [u8]) -> Result<(), Errno> {
+		// Reading the ISR status register acknowledges the interrupt it may have raised.
+		let _ = self.reg::<u8>(reg::ISR_STATUS).read();
+
+		let Some((head, len)) = self.rx.reclaim() else {
+			return Ok(());
+		};
+
+		let payload_len = (len as usize).saturating_sub(core::mem::size_of::<NetHdr>());
+		let copy_len = core::cmp::min(payload_len, buff.len());
+		let data = self.rx_buffers[head as usize].as_slice();
+		buff[..copy_len]
+			.copy_from_slice(&data[core::mem::size_of::<NetHdr>()..(core::mem::size_of::<NetHdr>() + copy_len)]);
+
+		// Give the descriptor back to the device for reuse.
+		self.rx.publish(head);
+		self.rx.num_used += 1;
+		self.reg::<u16>(reg::QUEUE_NOTIFY).write(QUEUE_RX);
+
+		Ok(())
+	}
+
+	/// Copies `buff`, prefixed with a [`NetHdr`], into a fresh buffer placed behind a single TX
+	/// descriptor, and notifies the device.
+	fn write(&mut self, buff: &BuffList<'_>) -> Result<(), Errno> {
+		// Reclaim whatever the device has already finished sending first, dropping their buffers
+		// and returning the descriptors to the free list, to make room for this packet.
+		while let Some((done, _)) = self.tx.reclaim() {
+			self.tx_buffers[done as usize] = None;
+			let prev_head = self.tx.free_head;
+			self.tx.desc_mut(done).next = prev_head;
+			self.tx.free_head = done;
+		}
+
+		// Every descriptor is in flight: `free_head` would otherwise hand out one the device may
+		// still be DMA-reading from. The caller is expected to retry once the device has drained
+		// some of the ring.
+		if self.tx.num_used >= QUEUE_SIZE {
+			return Err(errno!(EAGAIN));
+		}
+
+		let hdr = NetHdr {
+			flags: 0,
+			gso_type: 0,
+			hdr_len: 0,
+			gso_size: 0,
+			csum_start: 0,
+			csum_offset: 0,
+		};
+
+		let len = buff.len();
+		let mut packet = DmaBuffer::alloc((core::mem::size_of::<NetHdr>() + len)
+			.div_ceil(memory::PAGE_SIZE)
+			.max(1))?;
+		{
+			let slice = packet.as_slice_mut();
+			slice[..core::mem::size_of::<NetHdr>()].copy_from_slice(unsafe {
+				core::slice::from_raw_parts(&hdr as *const _ as *const u8, core::mem::size_of::<NetHdr>())
+			});
+			buff.copy_to(&mut slice[core::mem::size_of::<NetHdr>()..]);
+		}
+
+		let phys = packet.phys_addr() as u64;
+		let total_len = (core::mem::size_of::<NetHdr>() + len) as u32;
+		let head = self.tx.free_head;
+		self.tx_buffers[head as usize] = Some(packet);
+
+		let desc = self.tx.desc_mut(head);
+		desc.addr = phys;
+		desc.len = total_len;
+		desc.flags = 0;
+		let next_free = desc.next;
+		self.tx.free_head = next_free;
+
+		self.tx.publish(head);
+		self.tx.num_used += 1;
+		self.reg::<u16>(reg::QUEUE_NOTIFY).write(QUEUE_TX);
+
+		Ok(())
+	}
+}
@@ -2,17 +2,20 @@
 //! to switch to another process that is in running state. The interruption is fired by the PIT
 //! on IDT0.
 //!
-//! A scheduler cycle is a period during which the scheduler iterates through every processes.
-//! The scheduler works by assigning a number of quantum for each process, based on the number of
-//! running processes and their priority.
-//! This number represents the number of ticks during which the process keeps running until
-//! switching to the next process.
+//! Picking which process runs next follows a virtual-runtime (CFS-style) model: every process
+//! accrues a `vruntime`, weighted inversely by its priority, for every tick it spends running,
+//! and the scheduler always picks whichever runnable process has accrued the least of it. This
+//! is starvation-free (a process that hasn't run in a while has the smallest vruntime and so is
+//! picked first) and priority-proportional (a higher-priority process accrues vruntime more
+//! slowly, so it comes up for scheduling more often) without needing an explicit quantum
+//! heuristic.
 
 use core::cmp::max;
 use core::ffi::c_void;
 use crate::errno::Errno;
 use crate::event::CallbackHook;
 use crate::event;
+use crate::file::fs::procfs;
 use crate::gdt;
 use crate::idt::pic;
 use crate::memory::malloc;
@@ -24,19 +27,46 @@ use crate::process::pid::Pid;
 use crate::process::tss;
 use crate::process;
 use crate::util::container::binary_tree::BinaryTree;
-use crate::util::container::binary_tree::BinaryTreeMutIterator;
 use crate::util::container::binary_tree::TraversalType;
 use crate::util::container::vec::Vec;
 use crate::util::lock::mutex::*;
-use crate::util::math;
 use crate::util::ptr::SharedPtr;
 
 /// The size of the temporary stack for context switching.
 const TMP_STACK_SIZE: usize = memory::PAGE_SIZE;
-/// The number of quanta for the process with the average priority.
-const AVERAGE_PRIORITY_QUANTA: usize = 10;
-/// The number of quanta for the process with the maximum priority.
-const MAX_PRIORITY_QUANTA: usize = 30;
+
+/// The scheduling weight of the default (`nice` 0) priority: a process with this weight accrues
+/// one unit of vruntime per tick it runs.
+const NICE_0_WEIGHT: u64 = 1024;
+/// The maximum vruntime head start a process is given when it wakes up after sleeping, expressed
+/// in ticks below [`Scheduler::min_vruntime`]. Keeps a long sleep from letting a process
+/// monopolize the CPU once it wakes back up.
+const WAKEUP_LATENCY_TICKS: u64 = 20;
+
+/// Returns the ID of the CPU core executing the calling code.
+///
+/// This reads the core's initial local APIC ID out of `cpuid` leaf `1` (bits 24..32 of `ebx`),
+/// which every core reports distinctly regardless of whether this kernel has brought it up. This
+/// kernel doesn't bring up secondary cores yet (there is no APIC/trampoline code), so in practice
+/// every call currently happens on the bootstrap processor, whose APIC ID is `0`. The scheduling
+/// below is written per-core against this function instead of a literal `0` so that wiring up
+/// real multicore boot only requires bringing the other cores up, not touching the scheduler.
+fn current_core_id() -> usize {
+	let ebx: u32;
+	unsafe {
+		core::arch::asm!(
+			"mov {tmp}, ebx",
+			"mov eax, 1",
+			"cpuid",
+			"xchg ebx, {tmp}",
+			tmp = out(reg) ebx,
+			out("eax") _,
+			out("ecx") _,
+			out("edx") _,
+		);
+	}
+	(ebx >> 24) as usize
+}
 
 /// The structure containing the context switching data.
 struct ContextSwitchData {
@@ -56,15 +86,18 @@ pub struct Scheduler {
 	/// The total number of ticks since the instanciation of the scheduler.
 	total_ticks: u64,
 
-	/// A binary tree containing all processes registered to the current scheduler.
+	/// A binary tree containing all processes registered to the current scheduler, keyed by PID.
 	processes: BinaryTree<Pid, SharedPtr<Process>>,
-	/// The currently running process with its PID.
-	curr_proc: Option<(Pid, SharedPtr<Process>)>,
-
-	/// The sum of all priorities, used to compute the average priority.
-	priority_sum: usize,
-	/// The priority of the processs which has the current highest priority.
-	priority_max: usize,
+	/// The same processes as `processes`, keyed by `(vruntime, pid)` instead, so that iterating it
+	/// in order yields the least-run process first. The PID is part of the key purely to keep it
+	/// unique, since two processes can share a vruntime.
+	runqueue: BinaryTree<(u64, Pid), SharedPtr<Process>>,
+	/// The vruntime of the process most recently picked to run, or the previous value if that is
+	/// larger, so it never decreases. Used as the reference point a woken process's vruntime is
+	/// clamped against.
+	min_vruntime: u64,
+	/// The process currently running on each core, with its PID, indexed by core id.
+	curr_procs: Vec<Option<(Pid, SharedPtr<Process>)>>,
 }
 
 impl Scheduler {
@@ -72,9 +105,11 @@ impl Scheduler {
 	pub fn new(cores_count: usize) -> Result<SharedPtr<Self>, Errno> {
 		let mut tmp_stacks = Vec::new();
 		let mut ctx_switch_data = Vec::new();
+		let mut curr_procs = Vec::new();
 		for _ in 0..cores_count {
 			tmp_stacks.push(malloc::Alloc::new_default(TMP_STACK_SIZE)?)?;
 			ctx_switch_data.push(None)?;
+			curr_procs.push(None)?;
 		}
 
 		let callback = | _id: u32, _code: u32, regs: &Regs, ring: u32 | {
@@ -89,10 +124,9 @@ impl Scheduler {
 			total_ticks: 0,
 
 			processes: BinaryTree::new(),
-			curr_proc: None,
-
-			priority_sum: 0,
-			priority_max: 0,
+			runqueue: BinaryTree::new(),
+			min_vruntime: 0,
+			curr_procs,
 		})
 	}
 
@@ -112,33 +146,46 @@ impl Scheduler {
 		Some(self.processes.get(pid)?.clone())
 	}
 
-	/// Returns the current running process. If no process is running, the function returns None.
+	/// Returns the process currently running on the calling core. If no process is running there,
+	/// the function returns None.
 	pub fn get_current_process(&mut self) -> Option<SharedPtr<Process>> {
-		Some(self.curr_proc.as_ref().cloned()?.1)
+		self.get_current_process_on(current_core_id())
 	}
 
-	/// Updates the scheduler's heuristic with the new priority of a process.
-	/// `old` is the old priority of the process.
-	/// `new` is the new priority of the process.
-	/// The function doesn't need to know the process which has been updated since it updates
-	/// global informations.
-	pub fn update_priority(&mut self, old: usize, new: usize) {
-		self.priority_sum = self.priority_sum - old + new;
+	/// Returns the process currently running on core `core_id`. If no process is running there,
+	/// the function returns None.
+	pub fn get_current_process_on(&mut self, core_id: usize) -> Option<SharedPtr<Process>> {
+		Some(self.curr_procs.get(core_id)?.as_ref().cloned()?.1)
+	}
 
-		if new >= self.priority_max {
-			self.priority_max = new;
-		}
+	/// Returns the scheduling weight of priority `priority`: the higher the priority, the larger
+	/// the weight, so [`Self::vruntime_delta`] grows more slowly and the process gets picked to
+	/// run more often.
+	fn weight(priority: usize) -> u64 {
+		NICE_0_WEIGHT + priority as u64 * 64
+	}
 
-		// FIXME: Unable to determine priority_max when new < old
+	/// Returns the vruntime to add to a process of the given `priority` after it has run for
+	/// `ticks` ticks.
+	fn vruntime_delta(ticks: u64, priority: usize) -> u64 {
+		ticks * NICE_0_WEIGHT / Self::weight(priority)
 	}
 
 	/// Adds a process to the scheduler.
 	pub fn add_process(&mut self, process: Process) -> Result<SharedPtr<Process>, Errno> {
 		let pid = process.get_pid();
-		let priority = process.get_priority();
-		let ptr = SharedPtr::new(process)?;
+		// Starting at the current floor keeps a newly-created process from being favored (too
+		// low) or starved (too high) relative to processes already running.
+		let vruntime = self.min_vruntime;
+		let mut ptr = SharedPtr::new(process)?;
+		ptr.lock(false).get_mut().set_vruntime(vruntime);
+
 		self.processes.insert(pid, ptr.clone())?;
-		self.update_priority(0, priority);
+		self.runqueue.insert((vruntime, pid), ptr.clone())?;
+
+		// Best-effort: a process must be schedulable even if exposing it under /proc fails (e.g.
+		// out of memory).
+		let _ = procfs::mount::add_process(pid);
 
 		Ok(ptr)
 	}
@@ -146,117 +193,109 @@ impl Scheduler {
 	/// Removes the process with the given pid `pid`.
 	pub fn remove_process(&mut self, pid: Pid) {
 		if let Some(mut proc_mutex) = self.get_by_pid(pid) {
-			let guard = proc_mutex.lock(false);
-			let process = guard.get();
+			let vruntime = proc_mutex.lock(false).get().get_vruntime();
 
-			let priority = process.get_priority();
 			self.processes.remove(pid);
-			self.update_priority(priority, 0);
+			self.runqueue.remove((vruntime, pid));
+
+			procfs::mount::remove_process(pid);
 		}
 	}
 
-	// TODO Clean
-	/// Returns the average priority of a process.
-	/// `priority_sum` is the sum of all processes' priorities.
-	/// `processes_count` is the number of processes.
-	fn get_average_priority(priority_sum: usize, processes_count: usize) -> usize {
-		priority_sum / processes_count
-	}
+	/// Called when a process transitions out of a sleeping or blocked state, right before it
+	/// becomes runnable again.
+	///
+	/// A process that slept for a long time would otherwise come back with an unfairly small
+	/// vruntime relative to everything that kept running while it was away, letting it
+	/// monopolize the CPU once woken; this clamps it up to no less than
+	/// `min_vruntime - WAKEUP_LATENCY_TICKS` to bound how large a head start it can get.
+	pub fn wake(&mut self, pid: Pid) -> Result<(), Errno> {
+		let Some(mut proc_mutex) = self.get_by_pid(pid) else {
+			return Ok(());
+		};
+
+		let (old_vruntime, new_vruntime) = {
+			let mut guard = proc_mutex.lock(false);
+			let proc = guard.get_mut();
+
+			let old_vruntime = proc.get_vruntime();
+			let floor = self.min_vruntime.saturating_sub(WAKEUP_LATENCY_TICKS);
+			let new_vruntime = max(old_vruntime, floor);
+			proc.set_vruntime(new_vruntime);
 
-	// TODO Clean
-	/// Returns the number of quantum for the given priority.
-	/// `priority` is the process's priority.
-	/// `priority_sum` is the sum of all processes' priorities.
-	/// `priority_max` is the highest priority a process currently has.
-	/// `processes_count` is the number of processes.
-	fn get_quantum_count(priority: usize, priority_sum: usize, priority_max: usize,
-		processes_count: usize) -> usize {
-		let n = math::integer_linear_interpolation::<isize>(priority as _,
-			Self::get_average_priority(priority_sum, processes_count) as _,
-			priority_max as _,
-			AVERAGE_PRIORITY_QUANTA as _,
-			MAX_PRIORITY_QUANTA as _);
-		max(1, n) as _
+			(old_vruntime, new_vruntime)
+		};
+
+		if new_vruntime != old_vruntime {
+			self.runqueue.remove((old_vruntime, pid));
+			self.runqueue.insert((new_vruntime, pid), proc_mutex)?;
+		}
+
+		Ok(())
 	}
 
-	// TODO Clean
-	/// Tells whether the given process `process` can run.
-	fn can_run(process: &Process, _priority_sum: usize, _priority_max: usize,
-		_processes_count: usize) -> bool {
-		if process.get_state() == process::State::Running {
-			// TODO fix
-			//process.quantum_count < Self::get_quantum_count(process.get_priority(), priority_sum,
-			//	priority_max, processes_count)
-			true
-		} else {
-			false
+	/// Tells whether the given process `process` can run on core `core_id`.
+	fn can_run(process: &Process, core_id: usize) -> bool {
+		if process.get_state() != process::State::Running {
+			return false;
 		}
+		// The affinity mask is a bit array sized to the number of cores, like Linux's
+		// `cpu_set_t`: bit `core_id` set means the process may be scheduled on that core.
+		process.get_cpu_affinity() & (1 << core_id) != 0
 	}
 
-	// TODO Clean
-	/// Returns the next process to run with its PID. If the process is changed, the quantum count
-	/// of the previous process is reset.
-	fn get_next_process(&mut self) -> Option<(Pid, SharedPtr<Process>)> {
-		let priority_sum = self.priority_sum;
-		let priority_max = self.priority_max;
-		let processes_count = self.processes.count();
-		// If no process exist, nothing to run
-		if processes_count == 0 {
+	/// Returns the next process to run on core `core_id`, with its PID: the runnable process
+	/// with the smallest vruntime, i.e. the leftmost entry of `runqueue` that isn't already bound
+	/// to another core. If the process changes, the quantum count of the previous process running
+	/// on this core is reset.
+	///
+	/// A process currently running on another core is never picked, even if it would otherwise be
+	/// runnable, so that no two cores ever end up executing the same process at once.
+	fn get_next_process(&mut self, core_id: usize) -> Option<(Pid, SharedPtr<Process>)> {
+		if self.runqueue.count() == 0 {
 			return None;
 		}
 
-		// Getting the current process, or take the first process in the list if no process is
-		// running
-		let (curr_pid, mut curr_proc) = self.curr_proc.clone().or_else(|| {
-			let (pid, proc) = self.processes.get_min(0)?;
-			Some((*pid, proc.clone()))
-		})?;
-
-		// Closure iterating the tree to find an available process
-		let next = | iter: &mut BinaryTreeMutIterator<Pid, SharedPtr<Process>>, i: &mut usize | {
-			let mut proc: Option<(Pid, SharedPtr<Process>)> = None;
-
-			// Iterating over processes
-			while let Some((pid, process)) = iter.next() {
-				let runnable = {
-					let guard = process.lock(false);
-					Self::can_run(guard.get(), priority_sum, priority_max, processes_count)
-				};
-				if runnable {
-					proc = Some((*pid, process.clone()));
-					break;
-				}
-
-				*i += 1;
-				if *i >= processes_count {
-					break;
-				}
+		// The PIDs currently bound to another core, which must not be stolen from it
+		let mut busy_elsewhere = Vec::new();
+		for (i, curr) in self.curr_procs.iter().enumerate() {
+			if i == core_id {
+				continue;
+			}
+			if let Some((pid, _)) = curr {
+				busy_elsewhere.push(*pid).unwrap();
 			}
+		}
 
-			proc
-		};
+		let curr_pid = self.curr_procs[core_id].as_ref().map(|(pid, _)| *pid);
 
-		let mut iter = self.processes.iter_mut();
-		// Setting the iterator next to the current running process
-		iter.jump(&curr_pid);
-		iter.next();
-
-		// The number of processes checked so far
-		let mut i = 0;
-
-		// Running the loop to reach the end of processes list
-		let mut next_proc = next(&mut iter, &mut i);
-		// If no suitable process is found, going back to the beginning to check the processes
-		// located before the previous process
-		if next_proc.is_none() && i < processes_count {
-			iter = self.processes.iter_mut();
-			next_proc = next(&mut iter, &mut i);
+		let mut next_proc = None;
+		let mut iter = self.runqueue.iter_mut();
+		while let Some((key, process)) = iter.next() {
+			let (vruntime, pid) = key;
+			if busy_elsewhere.iter().any(|p| p == pid) {
+				continue;
+			}
+
+			let runnable = {
+				let guard = process.lock(false);
+				Self::can_run(guard.get(), core_id)
+			};
+			if runnable {
+				next_proc = Some((*vruntime, *pid, process.clone()));
+				break;
+			}
 		}
 
-		let (next_pid, next_proc) = next_proc?;
+		let (next_vruntime, next_pid, next_proc) = next_proc?;
+		// `min_vruntime` never decreases: a woken process clamped slightly below it can still be
+		// picked, but it doesn't drag the floor back down.
+		self.min_vruntime = max(self.min_vruntime, next_vruntime);
 
-		if next_pid != curr_pid || processes_count == 1 {
-			curr_proc.lock(false).get_mut().quantum_count = 0;
+		if Some(next_pid) != curr_pid {
+			if let Some(mut curr_proc) = curr_pid.and_then(|pid| self.get_by_pid(pid)) {
+				curr_proc.lock(false).get_mut().quantum_count = 0;
+			}
 		}
 		Some((next_pid, next_proc))
 	}
@@ -275,20 +314,44 @@ impl Scheduler {
 
 		scheduler.total_ticks += 1;
 
-		// If a process is running, save its registers
-		if let Some(mut curr_proc) = scheduler.get_current_process() {
-			let mut guard = curr_proc.lock(false);
-			let curr_proc = guard.get_mut();
+		let core_id = current_core_id();
+
+		// If a process is running on this core, save its registers and charge it for the tick it
+		// just used, weighted by its priority.
+		if let Some(mut curr_proc) = scheduler.get_current_process_on(core_id) {
+			let pid = scheduler.curr_procs[core_id].as_ref().unwrap().0;
+
+			let (old_vruntime, new_vruntime) = {
+				let mut guard = curr_proc.lock(false);
+				let curr_proc = guard.get_mut();
+
+				curr_proc.regs = *regs;
+				curr_proc.syscalling = ring < 3;
 
-			curr_proc.regs = *regs;
-			curr_proc.syscalling = ring < 3;
+				let old_vruntime = curr_proc.get_vruntime();
+				let new_vruntime = old_vruntime + Self::vruntime_delta(1, curr_proc.get_priority());
+				curr_proc.set_vruntime(new_vruntime);
+
+				(old_vruntime, new_vruntime)
+			};
+
+			// Insert the new entry before removing the old one, so a failed (allocating) insert
+			// under memory pressure — plausible here, since that's exactly when a timer tick is
+			// likely to fire — leaves the process at its old runqueue position instead of
+			// dropping it out of the runqueue entirely. There's nowhere to propagate an error
+			// from this interrupt handler, so undo the vruntime bump on failure instead of
+			// unwrapping and panicking.
+			if scheduler.runqueue.insert((new_vruntime, pid), curr_proc.clone()).is_ok() {
+				scheduler.runqueue.remove((old_vruntime, pid));
+			} else {
+				curr_proc.lock(false).get_mut().set_vruntime(old_vruntime);
+			}
 		}
 
-		if let Some(next_proc) = &mut scheduler.get_next_process() {
-			// Set the process as current
-			scheduler.curr_proc = Some(next_proc.clone());
+		if let Some(next_proc) = &mut scheduler.get_next_process(core_id) {
+			// Set the process as current for this core
+			scheduler.curr_procs[core_id] = Some(next_proc.clone());
 
-			let core_id = 0; // TODO
 			let f = | data | {
 				let (syscalling, regs) = {
 					let data = unsafe {
@@ -324,7 +387,7 @@ impl Scheduler {
 				scheduler.tmp_stacks[core_id].as_ptr_mut() as *mut c_void
 			};
 			scheduler.ctx_switch_data[core_id] = Some(ContextSwitchData {
-				proc: scheduler.curr_proc.as_mut().unwrap().1.clone(),
+				proc: scheduler.curr_procs[core_id].as_mut().unwrap().1.clone(),
 			});
 			let ctx_switch_data_ptr = &mut scheduler.ctx_switch_data[core_id] as *mut _;
 
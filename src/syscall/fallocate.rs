@@ -0,0 +1,51 @@
+//! The `fallocate` syscall manipulates the allocated disk space of a file, allowing callers to
+//! preallocate space ahead of writes or punch holes in sparse regions.
+
+use crate::errno::Errno;
+use crate::file::fs::tmp;
+use crate::file::fs::Filesystem;
+use crate::file::FileLocation;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall]
+pub fn fallocate(fd: c_int, mode: u32, offset: i64, len: i64) -> Result<i32, Errno> {
+	if fd < 0 || offset < 0 || len <= 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::get_current().unwrap();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let open_fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = open_fd.get_open_file()?;
+	let open_file = open_file_mutex.lock();
+
+	let FileLocation::Filesystem { mountpoint_id, inode } = *open_file.get_location() else {
+		return Err(errno!(ESPIPE));
+	};
+
+	// Files created by `memfd_create` are backed by the anonymous tmpfs instance, which has no
+	// mountpoint (and thus no `mountpoint::from_id` entry) to look up.
+	if mountpoint_id == tmp::ANONYMOUS_MOUNTPOINT_ID {
+		let tmpfs_mutex = tmp::get_anonymous();
+		let mut tmpfs = tmpfs_mutex.lock();
+		tmpfs.get_mut().fallocate(&mut tmp::NullIo, inode, mode, offset as u64, len as u64)?;
+
+		return Ok(0);
+	}
+
+	let mountpoint_mutex = crate::file::mountpoint::from_id(mountpoint_id).ok_or(errno!(EBADF))?;
+	let mountpoint = mountpoint_mutex.lock();
+	let io_mutex = mountpoint.get_source().get_io()?;
+	let mut io = io_mutex.lock();
+	let fs_mutex = mountpoint.get_filesystem();
+	let mut fs = fs_mutex.lock();
+
+	fs.fallocate(&mut *io, inode, mode, offset as u64, len as u64)?;
+
+	Ok(0)
+}
@@ -0,0 +1,77 @@
+//! The `fcntl` syscall allows to manipulate a file descriptor.
+
+use crate::errno::Errno;
+use crate::file::fs::tmp;
+use crate::file::FileLocation;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Duplicates the file descriptor.
+const F_DUPFD: i32 = 0;
+/// Returns the file descriptor's close-on-exec flag.
+const F_GETFD: i32 = 1;
+/// Sets the file descriptor's close-on-exec flag.
+const F_SETFD: i32 = 2;
+/// Returns the file access mode and status flags.
+const F_GETFL: i32 = 3;
+/// Sets the file status flags.
+const F_SETFL: i32 = 4;
+/// Adds seals to the file.
+const F_ADD_SEALS: i32 = 1033;
+/// Returns the seals currently applied to the file.
+const F_GET_SEALS: i32 = 1034;
+
+#[syscall]
+pub fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::get_current().unwrap();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let mut fds = fds_mutex.lock();
+	let open_fd = fds.get_fd_mut(fd as _).ok_or_else(|| errno!(EBADF))?;
+
+	match cmd {
+		F_DUPFD => {
+			let open_file = open_fd.get_open_file()?;
+			let new_fd = fds.create_fd(0, open_file)?;
+			Ok(new_fd.get_id() as _)
+		}
+
+		F_GETFD => Ok(open_fd.get_flags() as _),
+		F_SETFD => {
+			open_fd.set_flags(arg as _);
+			Ok(0)
+		}
+
+		// TODO Track per-open-file status flags (O_APPEND, O_NONBLOCK, ...)
+		F_GETFL | F_SETFL => Ok(0),
+
+		F_ADD_SEALS | F_GET_SEALS => {
+			let open_file_mutex = open_fd.get_open_file()?;
+			let open_file = open_file_mutex.lock();
+			let FileLocation::Filesystem { mountpoint_id, inode } = *open_file.get_location()
+				else {
+				return Err(errno!(EINVAL));
+			};
+			if mountpoint_id != tmp::ANONYMOUS_MOUNTPOINT_ID {
+				return Err(errno!(EINVAL));
+			}
+
+			let tmpfs_mutex = tmp::get_anonymous();
+			let mut tmpfs = tmpfs_mutex.lock();
+			if cmd == F_ADD_SEALS {
+				tmpfs.get_mut().add_seals(inode, arg as u32)?;
+				Ok(0)
+			} else {
+				Ok(tmpfs.get().get_seals(inode) as _)
+			}
+		}
+
+		_ => Err(errno!(EINVAL)),
+	}
+}
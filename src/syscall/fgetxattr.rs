@@ -0,0 +1,41 @@
+//! The `fgetxattr` syscall retrieves the value of an extended attribute on the file referred to
+//! by an open file descriptor.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::string::String;
+use core::ffi::c_int;
+use macros::syscall;
+use super::util::with_location;
+
+/// The implementation of the `fgetxattr` syscall.
+#[syscall]
+pub fn fgetxattr(fd: c_int, name: SyscallString, value: SyscallSlice<u8>, size: usize)
+	-> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let open_fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = open_fd.get_open_file()?;
+	let open_file = open_file_mutex.lock();
+	let location = *open_file.get_location();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+	let name = String::try_from(name)?;
+	let buf = value.get_mut(&mut mem_space_guard, size)?.ok_or_else(|| errno!(EFAULT))?;
+
+	let len = with_location(location, |fs, io, inode| fs.get_xattr(io, inode, &name, buf))?;
+
+	Ok(len as _)
+}
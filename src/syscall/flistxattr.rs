@@ -0,0 +1,36 @@
+//! The `flistxattr` syscall lists the extended attributes set on the file referred to by an open
+//! file descriptor.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+use super::util::with_location;
+
+/// The implementation of the `flistxattr` syscall.
+#[syscall]
+pub fn flistxattr(fd: c_int, list: SyscallSlice<u8>, size: usize) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let open_fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = open_fd.get_open_file()?;
+	let open_file = open_file_mutex.lock();
+	let location = *open_file.get_location();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	let buf = list.get_mut(&mut mem_space_guard, size)?.ok_or_else(|| errno!(EFAULT))?;
+
+	let len = with_location(location, |fs, io, inode| fs.list_xattr(io, inode, buf))?;
+
+	Ok(len as _)
+}
@@ -0,0 +1,31 @@
+//! The `getgroups` syscall returns the supplementary group IDs of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::Gid;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use macros::syscall;
+
+/// The implementation of the `getgroups` syscall.
+#[syscall]
+pub fn getgroups(size: usize, list: SyscallSlice<Gid>) -> Result<i32, Errno> {
+	let proc_mutex = Process::get_current().unwrap();
+	let proc = proc_mutex.lock();
+
+	let groups = proc.get_groups();
+	// A size of zero is a request for the number of supplementary groups without writing
+	// anything back.
+	if size == 0 {
+		return Ok(groups.len() as _);
+	}
+	if size < groups.len() {
+		return Err(errno!(EINVAL));
+	}
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	let list_slice = list.get_mut(&mut mem_space_guard, groups.len())?.ok_or(errno!(EFAULT))?;
+	list_slice.copy_from_slice(groups);
+
+	Ok(groups.len() as _)
+}
@@ -0,0 +1,45 @@
+//! The `llistxattr` syscall lists the extended attributes set on a file, without following a
+//! trailing symbolic link.
+
+use crate::errno::Errno;
+use crate::file::path::Path;
+use crate::file::vfs;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use macros::syscall;
+use super::util::get_absolute_path;
+use super::util::with_location;
+
+/// The implementation of the `llistxattr` syscall.
+#[syscall]
+pub fn llistxattr(path: SyscallString, list: SyscallSlice<u8>, size: usize) -> Result<i32, Errno> {
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get_mut();
+
+	let uid = proc.get_euid();
+	let gid = proc.get_egid();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mem_space_guard = mem_space.lock();
+	let path_str = path.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+	let abs_path = get_absolute_path(proc, Path::from_str(path_str, true)?)?;
+	drop(mem_space_guard);
+
+	let vfs_mutex = vfs::get();
+	let vfs_guard = vfs_mutex.lock();
+	let file_mutex = vfs_guard.get_mut().as_mut().unwrap()
+		.get_file_from_path(&abs_path, uid, gid, false)?;
+	let file_guard = file_mutex.lock();
+	let location = *file_guard.get().get_location();
+	drop(file_guard);
+	drop(vfs_guard);
+
+	let mut mem_space_guard = mem_space.lock();
+	let buf = list.get_mut(&mut mem_space_guard, size)?.ok_or_else(|| errno!(EFAULT))?;
+
+	let len = with_location(location, |fs, io, inode| fs.list_xattr(io, inode, buf))?;
+
+	Ok(len as _)
+}
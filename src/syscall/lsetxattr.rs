@@ -0,0 +1,58 @@
+//! The `lsetxattr` syscall sets the value of an extended attribute on a file, without following
+//! a trailing symbolic link.
+
+use crate::errno::Errno;
+use crate::file::path::Path;
+use crate::file::vfs;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use macros::syscall;
+use super::util::get_absolute_path;
+use super::util::with_location;
+
+/// The implementation of the `lsetxattr` syscall.
+#[syscall]
+pub fn lsetxattr(path: SyscallString, name: SyscallString, value: SyscallSlice<u8>, size: usize,
+	flags: c_int) -> Result<i32, Errno> {
+	let (uid, gid, abs_path, name, value) = {
+		let mutex = Process::get_current().unwrap();
+		let guard = mutex.lock();
+		let proc = guard.get_mut();
+
+		let uid = proc.get_euid();
+		let gid = proc.get_egid();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let path_str = path.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		let abs_path = get_absolute_path(proc, Path::from_str(path_str, true)?)?;
+
+		let name_str = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		let name = String::try_from(name_str)?;
+
+		let value = value.get(&mem_space_guard, size)?.ok_or_else(|| errno!(EFAULT))?;
+		let value = Vec::try_from(value)?;
+
+		(uid, gid, abs_path, name, value)
+	};
+
+	let _ = flags;
+
+	let vfs_mutex = vfs::get();
+	let vfs_guard = vfs_mutex.lock();
+	let file_mutex = vfs_guard.get_mut().as_mut().unwrap()
+		.get_file_from_path(&abs_path, uid, gid, false)?;
+	let file_guard = file_mutex.lock();
+	let location = *file_guard.get().get_location();
+
+	with_location(location, |fs, io, inode| {
+		fs.set_xattr(io, inode, &name, value.as_slice(), uid, gid)
+	})?;
+
+	Ok(0)
+}
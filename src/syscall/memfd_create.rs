@@ -0,0 +1,62 @@
+//! The `memfd_create` syscall creates an anonymous, tmpfs-backed file and returns a file
+//! descriptor referring to it, without ever linking it into a directory.
+
+use crate::errno::Errno;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file::OpenFile;
+use crate::file::FileLocation;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use macros::syscall;
+
+/// Close the file descriptor on `execve`.
+const MFD_CLOEXEC: u32 = 0x0001;
+/// Allow `fcntl`'s `F_ADD_SEALS`/`F_GET_SEALS` to be used on the returned file descriptor.
+const MFD_ALLOW_SEALING: u32 = 0x0002;
+
+/// The maximum length of the name given to `memfd_create`.
+const NAME_MAX_LEN: usize = 249;
+
+#[syscall]
+pub fn memfd_create(name: SyscallString, flags: u32) -> Result<i32, Errno> {
+	if flags & !(MFD_CLOEXEC | MFD_ALLOW_SEALING) != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::get_current().unwrap();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mem_space_guard = mem_space.lock();
+	let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+	if name.len() > NAME_MAX_LEN {
+		return Err(errno!(EINVAL));
+	}
+
+	let uid = proc.get_euid();
+	let gid = proc.get_egid();
+
+	// The anonymous node is created directly in the shared, internal tmpfs instance mounted for
+	// anonymous memory objects, and is never linked into any directory.
+	let tmpfs_mutex = crate::file::fs::tmp::get_anonymous();
+	let mut tmpfs = tmpfs_mutex.lock();
+	let inode = tmpfs.create_anonymous(uid, gid, flags & MFD_ALLOW_SEALING != 0)?;
+	drop(tmpfs);
+
+	let loc = FileLocation::Filesystem {
+		mountpoint_id: crate::file::fs::tmp::ANONYMOUS_MOUNTPOINT_ID,
+		inode,
+	};
+	let open_file = OpenFile::new(loc, None, crate::file::open_file::O_RDWR)?;
+
+	let mut fd_flags = 0;
+	if flags & MFD_CLOEXEC != 0 {
+		fd_flags |= FD_CLOEXEC;
+	}
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
@@ -0,0 +1,80 @@
+//! The `recvfrom` system call receives a message from a socket.
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use core::any::Any;
+use core::ffi::c_int;
+use macros::syscall;
+use super::sendto::MSG_DONTWAIT;
+
+/// Doesn't remove the data from the socket's receive queue; a subsequent read returns the same
+/// data.
+pub const MSG_PEEK: i32 = 0x02;
+/// Indicates that part of a datagram was discarded because it didn't fit in the supplied buffer.
+pub const MSG_TRUNC: i32 = 0x20;
+
+#[syscall]
+pub fn recvfrom(
+	sockfd: c_int,
+	buf: SyscallSlice<u8>,
+	len: usize,
+	flags: c_int,
+	src_addr: SyscallSlice<u8>,
+	addrlen: SyscallSlice<isize>,
+) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::get_current().unwrap();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let fd = fds.get_fd(sockfd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = fd.get_open_file()?;
+	let open_file = open_file_mutex.lock();
+	let sock_mutex = buffer::get_or_default::<Socket>(open_file.get_location())?;
+	let mut sock = sock_mutex.lock();
+	let sock = (&mut *sock as &mut dyn Any)
+		.downcast_mut::<Socket>()
+		.unwrap();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	let dontwait = flags & MSG_DONTWAIT != 0;
+	let peek = flags & MSG_PEEK != 0;
+
+	let mut tmp = crate::vec![0u8; len]?;
+	let (n, truncated, from) = sock.recv(&mut tmp, dontwait, peek)?;
+
+	let buf_slice = buf.get_mut(&mut mem_space_guard, n)?.ok_or(errno!(EFAULT))?;
+	buf_slice.copy_from_slice(&tmp[..n]);
+
+	if let Some(from) = from {
+		// `*addrlen` is the caller-supplied capacity of `src_addr`, not a hint: the copy must
+		// never exceed it, even when the real address (e.g. a `sockaddr_in6`) is larger.
+		let cap = match addrlen.get(&mem_space_guard, 1)? {
+			Some(len_slice) => len_slice[0].max(0) as usize,
+			None => 0,
+		};
+		let copy_len = core::cmp::min(cap, from.len());
+		if copy_len > 0 {
+			if let Some(addr_slice) = src_addr.get_mut(&mut mem_space_guard, copy_len)? {
+				addr_slice.copy_from_slice(&from[..copy_len]);
+			}
+		}
+		if let Some(len_slice) = addrlen.get_mut(&mut mem_space_guard, 1)? {
+			len_slice[0] = from.len() as _;
+		}
+	}
+
+	// `recvfrom` has no flags-out parameter to report `MSG_TRUNC` through; `recvmsg` does.
+	let _ = truncated;
+
+	Ok(n as _)
+}
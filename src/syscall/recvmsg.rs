@@ -0,0 +1,115 @@
+//! The `recvmsg` system call receives a message from a socket, scattering its payload across a
+//! scatter-gather list of buffers (`iovec`).
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use core::any::Any;
+use core::ffi::c_int;
+use core::mem::size_of;
+use macros::syscall;
+use super::recvfrom::MSG_PEEK;
+use super::sendmsg::IoVec;
+use super::sendmsg::MsgHdr;
+use super::sendto::MSG_DONTWAIT;
+
+/// Set in `msghdr::msg_flags` when the received datagram was larger than the buffers supplied
+/// and data was discarded.
+pub const MSG_TRUNC: i32 = 0x20;
+
+#[syscall]
+pub fn recvmsg(sockfd: c_int, msg: SyscallPtr<MsgHdr>, flags: c_int) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::get_current().unwrap();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let fd = fds.get_fd(sockfd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = fd.get_open_file()?;
+	let open_file = open_file_mutex.lock();
+	let sock_mutex = buffer::get_or_default::<Socket>(open_file.get_location())?;
+	let mut sock = sock_mutex.lock();
+	let sock = (&mut *sock as &mut dyn Any)
+		.downcast_mut::<Socket>()
+		.unwrap();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+
+	// Peek at the descriptors carried by the header, and validate every one of them before any
+	// slice is constructed from them, exactly as `sendmsg`'s `gather_iovecs` does.
+	let (msg_iov, msg_iovlen, msg_name, msg_namelen) = {
+		let hdr = msg.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
+		(hdr.msg_iov, hdr.msg_iovlen, hdr.msg_name, hdr.msg_namelen)
+	};
+
+	// For `SockDgram`, one call drains exactly one datagram (truncating what doesn't fit); for
+	// `SockStream`, bytes are coalesced across message boundaries until the buffers are full or
+	// the source is exhausted.
+	let mut total_cap = 0usize;
+	let mut iovs = crate::util::container::vec::Vec::new();
+	for i in 0..msg_iovlen {
+		if !mem_space_guard.can_access(unsafe { msg_iov.add(i) } as _, size_of::<IoVec>(), true, false) {
+			return Err(errno!(EFAULT));
+		}
+		let iov = unsafe { &*msg_iov.add(i) };
+		if iov.iov_len == 0 {
+			continue;
+		}
+		if !mem_space_guard.can_access(iov.iov_base, iov.iov_len, true, true) {
+			return Err(errno!(EFAULT));
+		}
+		iovs.push((iov.iov_base, iov.iov_len))?;
+		total_cap += iov.iov_len;
+	}
+	if !msg_name.is_null()
+		&& msg_namelen > 0
+		&& !mem_space_guard.can_access(msg_name, msg_namelen as usize, true, true)
+	{
+		return Err(errno!(EFAULT));
+	}
+
+	let dontwait = flags & MSG_DONTWAIT != 0;
+	let peek = flags & MSG_PEEK != 0;
+
+	let mut tmp = crate::vec![0u8; total_cap]?;
+	let (n, truncated, from) = sock.recv(&mut tmp, dontwait, peek)?;
+
+	// Write back through the already-validated locals, not a fresh read of `hdr`: the process
+	// could shrink or unmap the descriptors between the validation pass above and this write-back
+	// if `hdr` were re-read from user memory here, reopening the very race the validation pass
+	// above exists to close.
+	let mut written = 0;
+	for &(iov_base, iov_len) in iovs.iter() {
+		if written >= n {
+			break;
+		}
+		let chunk = core::cmp::min(iov_len, n - written);
+		if chunk == 0 {
+			continue;
+		}
+
+		let dst = unsafe { core::slice::from_raw_parts_mut(iov_base, chunk) };
+		dst.copy_from_slice(&tmp[written..(written + chunk)]);
+		written += chunk;
+	}
+
+	if let Some(from) = from {
+		if !msg_name.is_null() && msg_namelen > 0 {
+			let copy_len = core::cmp::min(msg_namelen as usize, from.len());
+			let dst = unsafe { core::slice::from_raw_parts_mut(msg_name, copy_len) };
+			dst.copy_from_slice(&from[..copy_len]);
+		}
+	}
+
+	let hdr = msg.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
+	hdr.msg_flags = if truncated { MSG_TRUNC } else { 0 };
+
+	Ok(written as _)
+}
@@ -0,0 +1,37 @@
+//! The `sched_getaffinity` syscall returns a process's CPU affinity mask.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::process;
+use core::cmp::min;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_getaffinity(pid: Pid, cpusetsize: usize, mask: SyscallSlice<u8>) -> Result<i32, Errno> {
+	if cpusetsize == 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = if pid == 0 {
+		Process::get_current().unwrap()
+	} else {
+		let sched_mutex = process::get_scheduler();
+		let mut sched_guard = sched_mutex.lock(false);
+		sched_guard.get_mut().get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?
+	};
+	let proc = proc_mutex.lock();
+	let affinity = proc.get_cpu_affinity();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	let len = min(cpusetsize, core::mem::size_of::<u64>());
+	let mask_slice = mask.get_mut(&mut mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
+	for (i, byte) in mask_slice.iter_mut().enumerate() {
+		*byte = (affinity >> (i * 8)) as u8;
+	}
+
+	// Like Linux, the return value is the number of bytes of the mask that were written.
+	Ok(len as i32)
+}
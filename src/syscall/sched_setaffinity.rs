@@ -0,0 +1,45 @@
+//! The `sched_setaffinity` syscall sets a process's CPU affinity mask, restricting the set of
+//! cores the scheduler is allowed to run it on.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::process;
+use core::cmp::min;
+use macros::syscall;
+
+#[syscall]
+pub fn sched_setaffinity(pid: Pid, cpusetsize: usize, mask: SyscallSlice<u8>) -> Result<i32, Errno> {
+	if cpusetsize == 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = if pid == 0 {
+		Process::get_current().unwrap()
+	} else {
+		let sched_mutex = process::get_scheduler();
+		let mut sched_guard = sched_mutex.lock(false);
+		sched_guard.get_mut().get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?
+	};
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	// The affinity mask is kept as a single `u64`, i.e. `cpu_set_t` truncated to 64 cores; bytes
+	// of a larger `cpusetsize` beyond that are simply not read.
+	let len = min(cpusetsize, core::mem::size_of::<u64>());
+	let mask_slice = mask.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
+
+	let mut affinity: u64 = 0;
+	for (i, byte) in mask_slice.iter().enumerate() {
+		affinity |= (*byte as u64) << (i * 8);
+	}
+	if affinity == 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	proc.set_cpu_affinity(affinity);
+
+	Ok(0)
+}
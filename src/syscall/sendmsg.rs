@@ -0,0 +1,118 @@
+//! The `sendmsg` system call sends a message on a socket, gathering its payload from a
+//! scatter-gather list of buffers (`iovec`).
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::net::buff::BuffList;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use core::any::Any;
+use core::ffi::c_int;
+use macros::syscall;
+use super::sendto::MSG_DONTWAIT;
+use super::sendto::MSG_NOSIGNAL;
+
+/// A scatter-gather buffer descriptor, as used by `readv`/`writev` and `sendmsg`/`recvmsg`.
+#[repr(C)]
+pub struct IoVec {
+	/// The base address of the buffer.
+	pub iov_base: *mut u8,
+	/// The length of the buffer, in bytes.
+	pub iov_len: usize,
+}
+
+/// The userspace-facing message header passed to `sendmsg`/`recvmsg`.
+#[repr(C)]
+pub struct MsgHdr {
+	/// A pointer to the socket address to send to/receive from, or `null` to use the socket's
+	/// connected peer.
+	pub msg_name: *mut u8,
+	/// The length in bytes of the buffer pointed to by `msg_name`.
+	pub msg_namelen: u32,
+	/// A pointer to the array of `iovec`s making up the payload.
+	pub msg_iov: *mut IoVec,
+	/// The number of entries in `msg_iov`.
+	pub msg_iovlen: usize,
+	/// A pointer to ancillary (control) data.
+	pub msg_control: *mut u8,
+	/// The length in bytes of the buffer pointed to by `msg_control`.
+	pub msg_controllen: usize,
+	/// Flags set on the received message (unused on send; filled in on receive).
+	pub msg_flags: c_int,
+}
+
+/// Reads the `iovec` array described by `hdr` into an owned buffer, gathering every segment in
+/// order.
+///
+/// `can_access` is used to validate each buffer before it is read, exactly as the `SyscallSlice`/
+/// `SyscallPtr` wrappers do internally.
+fn gather_iovecs<F: Fn(*const u8, usize) -> bool>(hdr: &MsgHdr, can_access: F)
+	-> Result<crate::util::container::vec::Vec<u8>, Errno> {
+	let mut buf = crate::util::container::vec::Vec::new();
+
+	for i in 0..hdr.msg_iovlen {
+		if !can_access(unsafe { hdr.msg_iov.add(i) } as _, core::mem::size_of::<IoVec>()) {
+			return Err(errno!(EFAULT));
+		}
+		let iov = unsafe { &*hdr.msg_iov.add(i) };
+		if iov.iov_len == 0 {
+			continue;
+		}
+		if !can_access(iov.iov_base, iov.iov_len) {
+			return Err(errno!(EFAULT));
+		}
+
+		let slice = unsafe { core::slice::from_raw_parts(iov.iov_base, iov.iov_len) };
+		buf.extend_from_slice(slice)?;
+	}
+
+	Ok(buf)
+}
+
+#[syscall]
+pub fn sendmsg(sockfd: c_int, msg: SyscallPtr<MsgHdr>, flags: c_int) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::get_current().unwrap();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let fd = fds.get_fd(sockfd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = fd.get_open_file()?;
+	let open_file = open_file_mutex.lock();
+	let sock_mutex = buffer::get_or_default::<Socket>(open_file.get_location())?;
+	let mut sock = sock_mutex.lock();
+	let sock = (&mut *sock as &mut dyn Any)
+		.downcast_mut::<Socket>()
+		.unwrap();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mem_space_guard = mem_space.lock();
+	let hdr = msg.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
+
+	if !hdr.msg_name.is_null() {
+		if !mem_space_guard.can_access(hdr.msg_name, hdr.msg_namelen as usize, true, false) {
+			return Err(errno!(EFAULT));
+		}
+		let addr = unsafe {
+			core::slice::from_raw_parts(hdr.msg_name, hdr.msg_namelen as usize)
+		};
+		sock.set_peer(addr)?;
+	}
+
+	let payload = gather_iovecs(hdr, |ptr, len| {
+		mem_space_guard.can_access(ptr as _, len, true, false)
+	})?;
+
+	let dontwait = flags & MSG_DONTWAIT != 0;
+	// `MSG_NOSIGNAL` is honored by the stream socket itself, which skips raising `SIGPIPE` on
+	// `EPIPE` when the flag was passed down with the send.
+	let nosignal = flags & MSG_NOSIGNAL != 0;
+	let n = sock.send_flagged(&BuffList::from(payload.as_slice()), dontwait, nosignal)?;
+
+	Ok(n as _)
+}
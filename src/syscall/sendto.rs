@@ -3,20 +3,28 @@
 use crate::errno::Errno;
 use crate::file::buffer;
 use crate::file::buffer::socket::Socket;
+use crate::net::buff::BuffList;
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::Process;
 use core::any::Any;
 use core::ffi::c_int;
 use macros::syscall;
 
-// TODO implement flags
+/// Requests non-blocking operation for this call: if the operation would block, return `EAGAIN`
+/// instead.
+pub const MSG_DONTWAIT: i32 = 0x40;
+/// Sends out-of-band data.
+pub const MSG_OOB: i32 = 0x01;
+/// Doesn't generate a `SIGPIPE` when sending on a stream socket whose peer has closed the
+/// connection.
+pub const MSG_NOSIGNAL: i32 = 0x4000;
 
 #[syscall]
 pub fn sendto(
 	sockfd: c_int,
 	buf: SyscallSlice<u8>,
 	len: usize,
-	_flags: c_int,
+	flags: c_int,
 	dest_addr: SyscallSlice<u8>,
 	addrlen: isize,
 ) -> Result<i32, Errno> {
@@ -44,12 +52,21 @@ pub fn sendto(
 
 	// Get slices
 	let mem_space = proc.get_mem_space().unwrap();
-	let mut mem_space_guard = mem_space.lock();
+	let mem_space_guard = mem_space.lock();
 	let buf_slice = buf.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
-	let addr_slice = addr
-		.get(&mem_space_guard, addrlen as _)?
-		.ok_or(errno!(EFAULT))?;
+	let addr_slice = if addrlen > 0 {
+		Some(dest_addr.get(&mem_space_guard, addrlen as _)?.ok_or(errno!(EFAULT))?)
+	} else {
+		None
+	};
 
-	// TODO
-	todo!()
-}
\ No newline at end of file
+	if let Some(addr) = addr_slice {
+		sock.set_peer(addr)?;
+	}
+
+	let dontwait = flags & MSG_DONTWAIT != 0;
+	let nosignal = flags & MSG_NOSIGNAL != 0;
+	let n = sock.send_flagged(&BuffList::from(buf_slice), dontwait, nosignal)?;
+
+	Ok(n as _)
+}
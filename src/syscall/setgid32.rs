@@ -0,0 +1,29 @@
+//! The `setgid32` syscall sets the GID of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::Gid;
+use crate::file::ROOT_UID;
+use crate::process::Process;
+use macros::syscall;
+
+/// The implementation of the `setgid32` syscall.
+#[syscall]
+pub fn setgid32(gid: Gid) -> Result<i32, Errno> {
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get_mut();
+
+	// A privileged process may set any GID; an unprivileged one may only set its effective GID
+	// to its current real or saved GID, leaving the other two untouched.
+	if proc.get_euid() == ROOT_UID {
+		proc.set_gid(gid);
+		proc.set_egid(gid);
+		proc.set_sgid(gid);
+	} else if gid == proc.get_gid() || gid == proc.get_sgid() {
+		proc.set_egid(gid);
+	} else {
+		return Err(errno!(EPERM));
+	}
+
+	Ok(0)
+}
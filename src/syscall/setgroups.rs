@@ -0,0 +1,39 @@
+//! The `setgroups` syscall sets the supplementary group IDs of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::Gid;
+use crate::file::ROOT_UID;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use macros::syscall;
+
+/// The maximum number of supplementary groups a process may belong to, matching Linux's default
+/// `NGROUPS_MAX`.
+const NGROUPS_MAX: usize = 65536;
+
+/// The implementation of the `setgroups` syscall.
+#[syscall]
+pub fn setgroups(size: usize, list: SyscallSlice<Gid>) -> Result<i32, Errno> {
+	if size > NGROUPS_MAX {
+		return Err(errno!(EINVAL));
+	}
+
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get_mut();
+
+	if proc.get_euid() != ROOT_UID {
+		return Err(errno!(EPERM));
+	}
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mem_space_guard = mem_space.lock();
+	if size == 0 {
+		proc.set_groups(&[])?;
+	} else {
+		let groups = list.get(&mem_space_guard, size)?.ok_or(errno!(EFAULT))?;
+		proc.set_groups(groups)?;
+	}
+
+	Ok(0)
+}
@@ -0,0 +1,56 @@
+//! The `setregid32` syscall sets the real and/or effective GID of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::Gid;
+use crate::file::ROOT_UID;
+use crate::process::Process;
+use macros::syscall;
+
+/// Value of a `rgid`/`egid` argument meaning "leave this ID unchanged", matching the `-1` passed
+/// by userspace (which wraps to this value since `gid_t` is unsigned).
+const NO_CHANGE: Gid = Gid::MAX;
+
+/// The implementation of the `setregid32` syscall.
+#[syscall]
+pub fn setregid32(rgid: Gid, egid: Gid) -> Result<i32, Errno> {
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get_mut();
+
+	let privileged = proc.get_euid() == ROOT_UID;
+	let old_rgid = proc.get_gid();
+	let old_egid = proc.get_egid();
+	let old_sgid = proc.get_sgid();
+
+	if !privileged {
+		if rgid != NO_CHANGE && rgid != old_rgid && rgid != old_egid {
+			return Err(errno!(EPERM));
+		}
+		if egid != NO_CHANGE && egid != old_rgid && egid != old_egid && egid != old_sgid {
+			return Err(errno!(EPERM));
+		}
+	}
+
+	let new_rgid = if rgid != NO_CHANGE {
+		rgid
+	} else {
+		old_rgid
+	};
+	let new_egid = if egid != NO_CHANGE {
+		egid
+	} else {
+		old_egid
+	};
+
+	proc.set_gid(new_rgid);
+	proc.set_egid(new_egid);
+	// If the real GID is changed, or the effective GID is set to a value other than the previous
+	// real GID, the saved GID follows the new effective GID. A pure no-op call (rgid == egid ==
+	// -1) must leave the saved GID untouched, even if the effective GID already differs from the
+	// real one.
+	if rgid != NO_CHANGE || (egid != NO_CHANGE && new_egid != old_rgid) {
+		proc.set_sgid(new_egid);
+	}
+
+	Ok(0)
+}
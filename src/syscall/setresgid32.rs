@@ -0,0 +1,44 @@
+//! The `setresgid32` syscall sets the real, effective and saved GID of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::Gid;
+use crate::file::ROOT_UID;
+use crate::process::Process;
+use macros::syscall;
+
+/// Value of a `rgid`/`egid`/`sgid` argument meaning "leave this ID unchanged", matching the `-1`
+/// passed by userspace (which wraps to this value since `gid_t` is unsigned).
+const NO_CHANGE: Gid = Gid::MAX;
+
+/// The implementation of the `setresgid32` syscall.
+#[syscall]
+pub fn setresgid32(rgid: Gid, egid: Gid, sgid: Gid) -> Result<i32, Errno> {
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get_mut();
+
+	let privileged = proc.get_euid() == ROOT_UID;
+	let old_rgid = proc.get_gid();
+	let old_egid = proc.get_egid();
+	let old_sgid = proc.get_sgid();
+
+	if !privileged {
+		for id in [rgid, egid, sgid] {
+			if id != NO_CHANGE && id != old_rgid && id != old_egid && id != old_sgid {
+				return Err(errno!(EPERM));
+			}
+		}
+	}
+
+	if rgid != NO_CHANGE {
+		proc.set_gid(rgid);
+	}
+	if egid != NO_CHANGE {
+		proc.set_egid(egid);
+	}
+	if sgid != NO_CHANGE {
+		proc.set_sgid(sgid);
+	}
+
+	Ok(0)
+}
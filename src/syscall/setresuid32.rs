@@ -0,0 +1,44 @@
+//! The `setresuid32` syscall sets the real, effective and saved UID of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::Uid;
+use crate::file::ROOT_UID;
+use crate::process::Process;
+use macros::syscall;
+
+/// Value of a `ruid`/`euid`/`suid` argument meaning "leave this ID unchanged", matching the `-1`
+/// passed by userspace (which wraps to this value since `uid_t` is unsigned).
+const NO_CHANGE: Uid = Uid::MAX;
+
+/// The implementation of the `setresuid32` syscall.
+#[syscall]
+pub fn setresuid32(ruid: Uid, euid: Uid, suid: Uid) -> Result<i32, Errno> {
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get_mut();
+
+	let privileged = proc.get_euid() == ROOT_UID;
+	let old_ruid = proc.get_uid();
+	let old_euid = proc.get_euid();
+	let old_suid = proc.get_suid();
+
+	if !privileged {
+		for id in [ruid, euid, suid] {
+			if id != NO_CHANGE && id != old_ruid && id != old_euid && id != old_suid {
+				return Err(errno!(EPERM));
+			}
+		}
+	}
+
+	if ruid != NO_CHANGE {
+		proc.set_uid(ruid);
+	}
+	if euid != NO_CHANGE {
+		proc.set_euid(euid);
+	}
+	if suid != NO_CHANGE {
+		proc.set_suid(suid);
+	}
+
+	Ok(0)
+}
@@ -0,0 +1,56 @@
+//! The `setreuid32` syscall sets the real and/or effective UID of the process's owner.
+
+use crate::errno::Errno;
+use crate::file::Uid;
+use crate::file::ROOT_UID;
+use crate::process::Process;
+use macros::syscall;
+
+/// Value of a `ruid`/`euid` argument meaning "leave this ID unchanged", matching the `-1` passed
+/// by userspace (which wraps to this value since `uid_t` is unsigned).
+const NO_CHANGE: Uid = Uid::MAX;
+
+/// The implementation of the `setreuid32` syscall.
+#[syscall]
+pub fn setreuid32(ruid: Uid, euid: Uid) -> Result<i32, Errno> {
+	let mutex = Process::get_current().unwrap();
+	let guard = mutex.lock();
+	let proc = guard.get_mut();
+
+	let privileged = proc.get_euid() == ROOT_UID;
+	let old_ruid = proc.get_uid();
+	let old_euid = proc.get_euid();
+	let old_suid = proc.get_suid();
+
+	if !privileged {
+		if ruid != NO_CHANGE && ruid != old_ruid && ruid != old_euid {
+			return Err(errno!(EPERM));
+		}
+		if euid != NO_CHANGE && euid != old_ruid && euid != old_euid && euid != old_suid {
+			return Err(errno!(EPERM));
+		}
+	}
+
+	let new_ruid = if ruid != NO_CHANGE {
+		ruid
+	} else {
+		old_ruid
+	};
+	let new_euid = if euid != NO_CHANGE {
+		euid
+	} else {
+		old_euid
+	};
+
+	proc.set_uid(new_ruid);
+	proc.set_euid(new_euid);
+	// If the real UID is changed, or the effective UID is set to a value other than the previous
+	// real UID, the saved UID follows the new effective UID. A pure no-op call (ruid == euid ==
+	// -1) must leave the saved UID untouched, even if the effective UID already differs from the
+	// real one.
+	if ruid != NO_CHANGE || (euid != NO_CHANGE && new_euid != old_ruid) {
+		proc.set_suid(new_euid);
+	}
+
+	Ok(0)
+}
@@ -13,14 +13,17 @@ pub fn setuid32(uid: Uid) -> Result<i32, Errno> {
 	let guard = mutex.lock();
 	let proc = guard.get_mut();
 
-	// TODO Implement correctly
-	if proc.get_uid() == ROOT_UID && proc.get_euid() == ROOT_UID {
+	// A privileged process may set any UID; an unprivileged one may only set its effective UID
+	// to its current real or saved UID, leaving the other two untouched.
+	if proc.get_euid() == ROOT_UID {
 		proc.set_uid(uid);
 		proc.set_euid(uid);
 		proc.set_suid(uid);
-
-		Ok(0)
+	} else if uid == proc.get_uid() || uid == proc.get_suid() {
+		proc.set_euid(uid);
 	} else {
-		Err(errno!(EPERM))
+		return Err(errno!(EPERM));
 	}
+
+	Ok(0)
 }
@@ -3,6 +3,8 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::fs::procfs;
+use crate::file::fs::Filesystem;
 use crate::file::mountpoint;
 use crate::file::path::Path;
 use crate::process::mem_space::ptr::SyscallString;
@@ -23,7 +25,11 @@ pub fn umount(target: SyscallString) -> Result<i32, Errno> {
 
 	// Getting the mountpoint
 	let target_path = Path::from_str(target_slice, true)?;
-	let _mountpoint = mountpoint::from_path(&target_path).ok_or(errno!(EINVAL))?;
+	let mountpoint_mutex = mountpoint::from_path(&target_path).ok_or(errno!(EINVAL))?;
+	let fs_id = mountpoint_mutex.lock().get_filesystem().lock().get().get_id();
+	// If the target happens to be procfs, stop routing process lifecycle events to it ahead of
+	// the actual unmount below.
+	procfs::mount::unregister(fs_id);
 
 	// TODO Check if busy (EBUSY)
 	// TODO If not, sync and unmount
@@ -4,6 +4,12 @@ pub mod at;
 
 use crate::errno;
 use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::tmp;
+use crate::file::fs::Filesystem;
+use crate::file::mountpoint;
+use crate::file::FileLocation;
+use crate::file::INode;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::regs::Regs;
 use crate::process::scheduler;
@@ -11,8 +17,37 @@ use crate::process::Process;
 use crate::process::State;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
+use crate::util::IO;
 use core::mem::size_of;
 
+/// Resolves `location` to its backing filesystem and IO device, then calls `f` with them along
+/// with the node's inode.
+///
+/// This is the same resolution `fallocate`/`fcntl` do by hand: an anonymous (`memfd_create`)
+/// location is routed directly to the shared anonymous tmpfs instance, since it has no
+/// mountpoint to look up; any other location is resolved through the mountpoint registry.
+pub fn with_location<R>(location: FileLocation,
+	f: impl FnOnce(&mut dyn Filesystem, &mut dyn IO, INode) -> Result<R, Errno>) -> Result<R, Errno> {
+	let FileLocation::Filesystem { mountpoint_id, inode } = location else {
+		return Err(errno!(ENOTSUP));
+	};
+
+	if mountpoint_id == tmp::ANONYMOUS_MOUNTPOINT_ID {
+		let tmpfs_mutex = tmp::get_anonymous();
+		let mut tmpfs = tmpfs_mutex.lock();
+		return f(tmpfs.get_mut(), &mut tmp::NullIo, inode);
+	}
+
+	let mountpoint_mutex = mountpoint::from_id(mountpoint_id).ok_or(errno!(EBADF))?;
+	let mountpoint = mountpoint_mutex.lock();
+	let io_mutex = mountpoint.get_source().get_io()?;
+	let mut io = io_mutex.lock();
+	let fs_mutex = mountpoint.get_filesystem();
+	let mut fs = fs_mutex.lock();
+
+	f(&mut *fs, &mut *io, inode)
+}
+
 // TODO Find a safer and cleaner solution
 /// Checks that the given array of strings at pointer `ptr` is accessible to
 /// process `proc`, then returns its content.
@@ -110,24 +145,30 @@ pub fn handle_proc_state() {
 /// ensure the mutex isn't already locked to prevent a deadlock.
 ///
 /// `regs` is the registers state passed to the current syscall.
+///
+/// If a signal is pending, the interrupted syscall is always left to report `EINTR` through its
+/// normal return path; `regs` resumes right where the syscall was interrupted.
+///
+/// TODO Honoring the handler's `SA_RESTART` flag requires rewinding `regs.eip` back to the
+/// address the syscall was entered at, which in turn requires the entry trampoline to record
+/// that address somewhere reachable from here. Neither exists yet, so `SA_RESTART` is currently
+/// ignored rather than rewound to a made-up address; wire this up once that plumbing lands.
 pub fn signal_check(regs: &Regs) {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
 
-	if proc.get_next_signal().is_some() {
-		// Returning the system call early to resume it later
-		let mut r = regs.clone();
-		// TODO Clean
-		r.eip -= 2; // TODO Handle the case where the instruction isn't two bytes long (sysenter)
-		proc.regs = r;
-		proc.syscalling = false;
+	if proc.get_next_signal().is_none() {
+		return;
+	}
 
-		// Switching to handle the signal
-		proc.prepare_switch();
+	proc.regs = regs.clone();
+	proc.syscalling = false;
 
-		drop(proc);
-		drop(proc_mutex);
+	// Switching to handle the signal
+	proc.prepare_switch();
 
-		handle_proc_state();
-	}
+	drop(proc);
+	drop(proc_mutex);
+
+	handle_proc_state();
 }
\ No newline at end of file